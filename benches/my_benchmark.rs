@@ -1,9 +1,15 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64,Ordering},
+};
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use criterion::async_executor::AsyncExecutor;
 use tokio::runtime::Runtime;
+use tokio::sync::RwLock;
+use moka::future::Cache;
+use geographiclib_rs::{Geodesic,InverseGeodesic};
 
-use memoized_kerney::{uncached_distance,Position,distance};
+use memoized_kerney::{uncached_distance,uncached_distance_on,BuildSeaHasher,DistanceData,Position,IntoPosition,distance,Ellipsoid,NearestIndex};
 
 const A: Position = Position::new(37.882704,-121.9807130);
 const B: Position = Position::new(37.883463,-121.980988);
@@ -17,5 +23,188 @@ pub fn async_benchmark(c: &mut Criterion) {
     c.bench_function("async_distance", |b| b.to_async(&rt).iter(|| async { distance(black_box(&A),black_box(&B)).await }));
 }
 
-criterion_group!(benches, baseline_benchmark,async_benchmark);
+/// The miss path before and after sharing solvers, on the same ellipsoid.
+///
+/// `geodesic_new` is the cost being saved. `*_new_solver` builds the solver for every query, what
+/// `uncached_distance_on` used to do, `*_shared_solver` is what it does
+/// now. The `cache_miss_*` pair wrap the same 2 solves in an otherwise
+/// identical moka cache, every iteration uses a new pair so it misses.
+pub fn geodesic_reuse_benchmark(c: &mut Criterion) {
+    c.bench_function("geodesic_new", |b| b.iter(|| {
+        Geodesic::new(black_box(Ellipsoid::GRS80.equatorial_radius()), black_box(Ellipsoid::GRS80.flattening()))
+    }));
+    for ellipsoid in [Ellipsoid::WGS84, Ellipsoid::GRS80] {
+        let name = if ellipsoid == Ellipsoid::WGS84 { "wgs84" } else { "grs80" };
+        c.bench_function(&format!("uncached_{}_new_solver", name), |b| b.iter(|| {
+            new_solver_distance(black_box(&ellipsoid), black_box(&A), black_box(&B))
+        }));
+        c.bench_function(&format!("uncached_{}_shared_solver", name), |b| b.iter(|| {
+            uncached_distance_on(black_box(&ellipsoid), black_box(&A), black_box(&B))
+        }));
+    }
+
+    let rt = Runtime::new().unwrap();
+    let new_solver: Cache<(Position,Position),f64> = Cache::new(10_000);
+    let counter = AtomicU64::new(0);
+    c.bench_function("cache_miss_grs80_new_solver", |b| b.to_async(&rt).iter(|| async {
+        let a = fresh(&counter);
+        new_solver.get_with((a, B), async { new_solver_distance(&Ellipsoid::GRS80, &a, &B) }).await
+    }));
+    let shared_solver: Cache<(Position,Position),f64> = Cache::new(10_000);
+    c.bench_function("cache_miss_grs80_shared_solver", |b| b.to_async(&rt).iter(|| async {
+        let a = fresh(&counter);
+        shared_solver.get_with((a, B), async { uncached_distance_on(&Ellipsoid::GRS80, &a, &B).distance }).await
+    }));
+}
+
+fn new_solver_distance(ellipsoid: &Ellipsoid, a: &Position, b: &Position) -> f64 {
+    let geod = Geodesic::new(ellipsoid.equatorial_radius(), ellipsoid.flattening());
+    let (s12, _, _, _): (f64,f64,f64,f64) = geod.inverse(a.get_lat(), a.get_lon(), b.get_lat(), b.get_lon());
+    s12
+}
+
+/// A pair never asked for before
+fn fresh(counter: &AtomicU64) -> Position {
+    let step = counter.fetch_add(1, Ordering::Relaxed) as f64;
+    Position::new(A.get_lat() + step * 1e-9, A.get_lon())
+}
+
+const TASKS: usize = 16;
+const LOOKUPS_PER_TASK: usize = 256;
+
+/// Every 8th lookup is a new pair, the rest are spread over 64 hot pairs.
+fn contended_pair(task: usize, lookup: usize, round: u64) -> (Position,Position) {
+    let hot = ((task * LOOKUPS_PER_TASK + lookup) % 64) as f64;
+    let a = if lookup.is_multiple_of(8) {
+        let fresh = (round as f64) * 1e-6 + ((task * LOOKUPS_PER_TASK + lookup) as f64) * 1e-12;
+        Position::new(A.get_lat() + fresh, A.get_lon())
+    } else {
+        Position::new(A.get_lat() + hot * 1e-4, A.get_lon())
+    };
+    (a, B)
+}
+
+/// The key both contention benches use, canonical and southern-most first
+/// like `DistanceCache`'s, so only the locking differs between them.
+fn contended_key(a: &Position, b: &Position) -> (Position,Position) {
+    let (a, b) = (a.canonical(), b.canonical());
+    if b < a { (b, a) } else { (a, b) }
+}
+
+/// The design `DistanceCache` replaced, a `RwLock` around the moka cache
+/// with a write lock taken on every miss.
+struct LockedCache {
+    cache: RwLock<Cache<(Position,Position),DistanceData,BuildSeaHasher>>,
+}
+impl LockedCache {
+    async fn distance(&self, a: &Position, b: &Position) -> DistanceData {
+        let key = contended_key(a, b);
+        if let Some(dist) = self.cache.read().await.get(&key).await {
+            return dist;
+        }
+        let dist = uncached_distance(&key.0, &key.1);
+        self.cache.write().await.insert(key, dist).await;
+        dist
+    }
+}
+
+/// What `DistanceCache` does now, the same cache with no lock of its own
+/// and single flight misses.
+struct LockFreeCache {
+    cache: Cache<(Position,Position),DistanceData,BuildSeaHasher>,
+}
+impl LockFreeCache {
+    async fn distance(&self, a: &Position, b: &Position) -> DistanceData {
+        let key = contended_key(a, b);
+        self.cache.get_with(key, async { uncached_distance(&key.0, &key.1) }).await
+    }
+}
+
+fn contended_cache() -> Cache<(Position,Position),DistanceData,BuildSeaHasher> {
+    Cache::builder().max_capacity(65536).build_with_hasher(BuildSeaHasher::default())
+}
+
+pub fn contention_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let locked = Arc::new(LockedCache { cache: RwLock::new(contended_cache()) });
+    let round = AtomicU64::new(0);
+    c.bench_function("contended_rwlock_cache", |b| b.to_async(&rt).iter(|| {
+        let locked = locked.clone();
+        let round = round.fetch_add(1, Ordering::Relaxed);
+        async move {
+            let handles: Vec<_> = (0..TASKS).map(|task| {
+                let locked = locked.clone();
+                tokio::spawn(async move {
+                    for lookup in 0..LOOKUPS_PER_TASK {
+                        let (a, b) = contended_pair(task, lookup, round);
+                        black_box(locked.distance(&a, &b).await);
+                    }
+                })
+            }).collect();
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        }
+    }));
+
+    let lock_free = Arc::new(LockFreeCache { cache: contended_cache() });
+    let round = AtomicU64::new(0);
+    c.bench_function("contended_lock_free_cache", |b| b.to_async(&rt).iter(|| {
+        let lock_free = lock_free.clone();
+        let round = round.fetch_add(1, Ordering::Relaxed);
+        async move {
+            let handles: Vec<_> = (0..TASKS).map(|task| {
+                let lock_free = lock_free.clone();
+                tokio::spawn(async move {
+                    for lookup in 0..LOOKUPS_PER_TASK {
+                        let (a, b) = contended_pair(task, lookup, round);
+                        black_box(lock_free.distance(&a, &b).await);
+                    }
+                })
+            }).collect();
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        }
+    }));
+}
+
+fn batch_pairs() -> Vec<(Position,Position)> {
+    (0..100_000).map(|i| {
+        let step = i as f64;
+        (Position::new(A.get_lat() + step * 1e-4, A.get_lon()), Position::new(B.get_lat(), B.get_lon() + step * 1e-4))
+    }).collect()
+}
+
+pub fn sequential_batch_benchmark(c: &mut Criterion) {
+    let pairs = batch_pairs();
+    c.bench_function("sequential_uncached_distances", |b| b.iter(|| {
+        pairs.iter().map(|(a, b)| uncached_distance(a, b)).collect::<Vec<_>>()
+    }));
+}
+
+/// 10 nearest of 100,000 scattered items, only a handful of geodesics are
+/// solved per query
+pub fn nearest_benchmark(c: &mut Criterion) {
+    let items: Vec<Position> = (0..100_000u64).map(|i| {
+        let x = i.wrapping_mul(0x9e3779b97f4a7c15);
+        Position::new((x >> 40) as f64 / (1u64 << 24) as f64 * 180.0 - 90.0, (x & 0xff_ffff) as f64 / (1u64 << 24) as f64 * 360.0 - 180.0)
+    }).collect();
+    let index = NearestIndex::new(items);
+    c.bench_function("nearest_10_of_100k", |b| b.iter(|| index.nearest(black_box(&A), 10)));
+}
+
+#[cfg(feature = "rayon")]
+pub fn parallel_batch_benchmark(c: &mut Criterion) {
+    let pairs = batch_pairs();
+    c.bench_function("par_uncached_distances", |b| b.iter(|| {
+        memoized_kerney::par_uncached_distances(black_box(&pairs))
+    }));
+}
+
+#[cfg(not(feature = "rayon"))]
+criterion_group!(benches, baseline_benchmark,async_benchmark,geodesic_reuse_benchmark,contention_benchmark,sequential_batch_benchmark,nearest_benchmark);
+#[cfg(feature = "rayon")]
+criterion_group!(benches, baseline_benchmark,async_benchmark,geodesic_reuse_benchmark,contention_benchmark,sequential_batch_benchmark,nearest_benchmark,parallel_batch_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,190 @@
+/// Lets a `DistanceCache` stop using itself when caching is a net loss.
+///
+/// The cache measures `distance` lookups over a window of `window` calls.
+/// With `O` the average time a lookup adds on top of solving, `h` the hit
+/// rate and `S` the average solve time, a cached call costs `O + (1 - h) S`
+/// against `S` for solving directly, so once `O > h S` the cache is
+/// bypassed, `distance` solves every pair without touching moka.
+///
+/// `S` is averaged over every window measured so far, so a window in
+/// which everything hit, a hot cache under contention, can still decide to
+/// bypass. Time spent waiting on another caller solving the same pair is
+/// not counted as lookup overhead.
+///
+/// After `probe_after` bypassed calls the cache is used again for another
+/// window to check whether the hit rate or contention has changed.
+///
+/// Results are computed for the quantized positions in both modes, so
+/// switching changes what `distance` returns by a rounding error at most.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Adaptive {
+    /// Number of lookups measured before deciding
+    pub window: u64,
+    /// Number of calls to bypass before measuring again
+    pub probe_after: u64,
+}
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            window: 1024,
+            probe_after: 16384,
+        }
+    }
+}
+
+#[cfg(any(feature = "async", feature = "sync"))]
+pub(crate) use self::bypass::{Bypass,Probe};
+
+#[cfg(any(feature = "async", feature = "sync"))]
+mod bypass {
+    use std::{
+        sync::{
+            atomic::{AtomicBool,AtomicU64,Ordering},
+        },
+        time::{Duration},
+    };
+
+    use crate::{Adaptive};
+
+    /// The measurements and decision behind `Adaptive`, inert when the cache
+    /// was built without it.
+    ///
+    /// Updates are relaxed atomics, under concurrency a window may be a few
+    /// samples short or long, which is fine for a heuristic.
+    pub(crate) struct Bypass {
+        config: Option<Adaptive>,
+        bypassing: AtomicBool,
+        lookups: AtomicU64,
+        misses: AtomicU64,
+        timed: AtomicU64,
+        overhead_nanos: AtomicU64,
+        total_misses: AtomicU64,
+        total_solve_nanos: AtomicU64,
+        solves_started: AtomicU64,
+        in_flight: AtomicU64,
+        skipped: AtomicU64,
+    }
+
+    /// What the solver was doing when a lookup started
+    #[derive(Clone,Copy,Debug)]
+    pub(crate) struct Probe {
+        solves_started: u64,
+        in_flight: bool,
+    }
+
+    impl Bypass {
+        pub(crate) fn new(config: Option<Adaptive>) -> Self {
+            Self {
+                config: config.map(|config| Adaptive {
+                    window: config.window.max(1),
+                    probe_after: config.probe_after.max(1),
+                }),
+                bypassing: AtomicBool::new(false),
+                lookups: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+                timed: AtomicU64::new(0),
+                overhead_nanos: AtomicU64::new(0),
+                total_misses: AtomicU64::new(0),
+                total_solve_nanos: AtomicU64::new(0),
+                solves_started: AtomicU64::new(0),
+                in_flight: AtomicU64::new(0),
+                skipped: AtomicU64::new(0),
+            }
+        }
+
+        /// Currently skipping the cache
+        pub(crate) fn is_bypassing(&self) -> bool {
+            self.bypassing.load(Ordering::Relaxed)
+        }
+
+        /// Should this call skip the cache, counts towards the next re-probe
+        pub(crate) fn should_bypass(&self) -> bool {
+            let config = match &self.config {
+                Option::Some(config) if self.is_bypassing() => config,
+                _ => return false,
+            };
+            let skipped = self.skipped.fetch_add(1, Ordering::Relaxed) + 1;
+            if skipped >= config.probe_after {
+                self.skipped.store(0, Ordering::Relaxed);
+                self.bypassing.store(false, Ordering::Relaxed);
+            }
+            true
+        }
+
+        /// A lookup is starting
+        pub(crate) fn begin_lookup(&self) -> Probe {
+            if self.config.is_none() {
+                return Probe { solves_started: 0, in_flight: false };
+            }
+            Probe {
+                solves_started: self.solves_started.load(Ordering::Relaxed),
+                in_flight: self.in_flight.load(Ordering::Relaxed) > 0,
+            }
+        }
+
+        /// A lookup missed and is about to run the solver
+        pub(crate) fn begin_solve(&self) {
+            if self.config.is_none() {
+                return;
+            }
+            self.solves_started.fetch_add(1, Ordering::Relaxed);
+            self.in_flight.fetch_add(1, Ordering::Relaxed);
+        }
+
+        /// The solver started by `begin_solve` took `took`
+        pub(crate) fn record_solve(&self, took: Duration) {
+            if self.config.is_none() {
+                return;
+            }
+            self.in_flight.fetch_sub(1, Ordering::Relaxed);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            self.total_misses.fetch_add(1, Ordering::Relaxed);
+            self.total_solve_nanos.fetch_add(took.as_nanos() as u64, Ordering::Relaxed);
+        }
+
+        /// A lookup took `took` in total, `solved` of it in its own solve, the
+        /// last lookup of a window decides whether to bypass.
+        ///
+        /// A lookup that did not solve but overlapped a solve may have been
+        /// waiting on another caller's, that wait is not lookup overhead so
+        /// its time is left out. It still counts towards the hit rate.
+        pub(crate) fn record_lookup(&self, probe: Probe, took: Duration, solved: Option<Duration>) {
+            let config = match &self.config {
+                Option::Some(config) => config,
+                Option::None => return,
+            };
+            let overhead = match solved {
+                Option::Some(solved) => Option::Some(took.saturating_sub(solved)),
+                Option::None if probe.in_flight || self.solves_started.load(Ordering::Relaxed) != probe.solves_started => Option::None,
+                Option::None => Option::Some(took),
+            };
+            if let Option::Some(overhead) = overhead {
+                self.timed.fetch_add(1, Ordering::Relaxed);
+                self.overhead_nanos.fetch_add(overhead.as_nanos() as u64, Ordering::Relaxed);
+            }
+            let lookups = self.lookups.fetch_add(1, Ordering::Relaxed) + 1;
+            if lookups < config.window {
+                return;
+            }
+            let lookups = self.lookups.swap(0, Ordering::Relaxed) as f64;
+            let misses = self.misses.swap(0, Ordering::Relaxed) as f64;
+            let timed = self.timed.swap(0, Ordering::Relaxed) as f64;
+            let overhead_nanos = self.overhead_nanos.swap(0, Ordering::Relaxed) as f64;
+            // solve time hardly changes, so it is averaged over every window
+            // and a window without misses can still decide
+            let total_misses = self.total_misses.load(Ordering::Relaxed) as f64;
+            let total_solve_nanos = self.total_solve_nanos.load(Ordering::Relaxed) as f64;
+            if lookups == 0.0 || timed == 0.0 || total_misses == 0.0 {
+                // another thread already decided, or nothing to compare with yet
+                return;
+            }
+            let overhead = overhead_nanos / timed;
+            let hit_rate = 1.0 - (misses / lookups).min(1.0);
+            let solve = total_solve_nanos / total_misses;
+            if overhead > hit_rate * solve {
+                self.skipped.store(0, Ordering::Relaxed);
+                self.bypassing.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
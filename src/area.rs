@@ -0,0 +1,124 @@
+use crate::{Ellipsoid,GeodesicData,IntoPosition,Position,lon_diff,normalize_azimuth,inverse::{solve_geodesic}};
+
+/// Direction the vertices of a polygon are listed in
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Area and perimeter of a polygon whose edges are geodesics.
+///
+/// Matches GeographicLib's Planimeter, `area` is signed, positive when the
+/// vertices are listed counter-clockwise, and lies in (-A/2, A/2] where
+/// `A` is the area of the whole ellipsoid. A polygon enclosing more than
+/// half the ellipsoid therefore reports the area outside of it with the
+/// opposite sign. Degenerate polygons, zero area, count as counter-clockwise.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct PolygonArea {
+    /// Signed area in square meters
+    pub area: f64,
+    /// Perimeter in meters, including the edge closing the polygon
+    pub perimeter: f64,
+    /// Winding of the vertices, from the sign of `area`
+    pub winding: Winding,
+}
+
+/// Accumulates edges into a `PolygonArea`.
+///
+/// Each edge's area (`S12`) is measured from the equator, so the sum only
+/// needs correcting for how many times the polygon wraps the prime
+/// meridian. This mirrors `geographiclib_rs::PolygonArea`, but takes edges
+/// that were already solved so they can come from a cache.
+pub(crate) struct Planimeter {
+    ellipsoid_area: f64,
+    perimeter: f64,
+    area: f64,
+    crossings: i64,
+}
+impl Planimeter {
+    /// `c2` is the ellipsoid's authalic radius squared
+    pub(crate) fn new(c2: f64) -> Self {
+        Self {
+            ellipsoid_area: 4.0 * std::f64::consts::PI * c2,
+            perimeter: 0.0,
+            area: 0.0,
+            crossings: 0,
+        }
+    }
+
+    /// Add the edge from `a` to `b`, `edge` must be the geodesic in that direction
+    pub(crate) fn add_edge(&mut self, a: Position, b: Position, edge: &GeodesicData) {
+        self.perimeter += edge.distance;
+        self.area += edge.area;
+        self.crossings += transit(a.get_lon(), b.get_lon());
+    }
+
+    pub(crate) fn finish(self) -> PolygonArea {
+        let whole = self.ellipsoid_area;
+        let mut area = self.area % whole;
+        if self.crossings % 2 != 0 {
+            area += if area < 0.0 { whole / 2.0 } else { -whole / 2.0 };
+        }
+        // edge areas add up clockwise
+        area = -area;
+        if area > whole / 2.0 {
+            area -= whole;
+        } else if area <= -whole / 2.0 {
+            area += whole;
+        }
+        PolygonArea {
+            area,
+            perimeter: self.perimeter,
+            winding: if area < 0.0 { Winding::Clockwise } else { Winding::CounterClockwise },
+        }
+    }
+}
+
+/// The edges of the closed polygon through `points`, polygons with fewer
+/// than 2 points have none.
+pub(crate) fn edges<A>(points: &[A]) -> impl Iterator<Item = (Position,Position)> + '_
+where
+    A: IntoPosition,
+{
+    let count = if points.len() < 2 { 0 } else { points.len() };
+    (0..count).map(move |i| (points[i].into_position(), points[(i + 1) % count].into_position()))
+}
+
+/// 1 or -1 if going from `lon1` to `lon2` crosses the prime meridian
+/// eastwards or westwards, otherwise 0. A longitude of +/-0 counts as
+/// east of the meridian.
+fn transit(lon1: f64, lon2: f64) -> i64 {
+    let lon12 = lon_diff(lon1, lon2);
+    let lon1 = normalize_azimuth(lon1);
+    let lon2 = normalize_azimuth(lon2);
+    if lon12 > 0.0 && ((lon1 < 0.0 && lon2 >= 0.0) || (lon1 > 0.0 && lon2 == 0.0)) {
+        1
+    } else if lon12 < 0.0 && lon1 >= 0.0 && lon2 < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+/// calculate the area and perimeter of a polygon on WGS84 without consulting any cache
+pub fn uncached_polygon_area<A>(points: &[A]) -> PolygonArea
+where
+    A: IntoPosition,
+{
+    uncached_polygon_area_on(&Ellipsoid::WGS84, points)
+}
+
+/// calculate the area and perimeter of a polygon on the supplied ellipsoid
+pub fn uncached_polygon_area_on<A>(ellipsoid: &Ellipsoid, points: &[A]) -> PolygonArea
+where
+    A: IntoPosition,
+{
+    ellipsoid.with_geodesic(|geod| {
+        let mut planimeter = Planimeter::new(ellipsoid.authalic_radius_squared());
+        for (a, b) in edges(points) {
+            planimeter.add_edge(a, b, &solve_geodesic(geod, a, b));
+        }
+        planimeter.finish()
+    })
+}
@@ -0,0 +1,69 @@
+use std::{
+    ops::{Index},
+};
+
+use crate::{DistanceData,IntoPosition,uncached_distance};
+
+/// Dense, row major, matrix of distances.
+///
+/// Row `i` holds the distances from the `i`th origin to every destination.
+#[derive(Clone,Debug,PartialEq)]
+pub struct DistanceMatrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<DistanceData>,
+}
+impl DistanceMatrix {
+    pub(crate) fn new(rows: usize, cols: usize, data: Vec<DistanceData>) -> Self {
+        debug_assert_eq!(rows * cols, data.len());
+        Self { rows, cols, data }
+    }
+
+    /// Number of origins
+    pub fn rows(&self) -> usize { self.rows }
+
+    /// Number of destinations
+    pub fn cols(&self) -> usize { self.cols }
+
+    /// Distance from origin `row` to destination `col`
+    pub fn get(&self, row: usize, col: usize) -> Option<&DistanceData> {
+        if row < self.rows && col < self.cols {
+            self.data.get(row * self.cols + col)
+        } else {
+            Option::None
+        }
+    }
+
+    /// Distances from origin `row` to every destination
+    pub fn row(&self, row: usize) -> &[DistanceData] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    /// Every distance, row major
+    pub fn as_slice(&self) -> &[DistanceData] {
+        &self.data
+    }
+
+    pub fn into_vec(self) -> Vec<DistanceData> {
+        self.data
+    }
+}
+impl Index<(usize,usize)> for DistanceMatrix {
+    type Output = DistanceData;
+    fn index(&self, (row, col): (usize,usize)) -> &DistanceData {
+        assert!(row < self.rows && col < self.cols, "({}, {}) is outside of a {}x{} matrix", row, col, self.rows, self.cols);
+        &self.data[row * self.cols + col]
+    }
+}
+
+/// calculate the distance from every origin to every destination without consulting any cache
+pub fn uncached_distance_matrix<A,B>(origins: &[A], destinations: &[B]) -> DistanceMatrix
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    let data = origins.iter()
+        .flat_map(|a| destinations.iter().map(move |b| uncached_distance(a, b)))
+        .collect();
+    DistanceMatrix::new(origins.len(), destinations.len(), data)
+}
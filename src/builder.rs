@@ -0,0 +1,104 @@
+use std::{
+    marker::{PhantomData},
+    time::{Duration},
+};
+
+use crate::{Adaptive,Ellipsoid,Quantization};
+
+/// Configures a `DistanceCache`.
+///
+/// Shared by `future::DistanceCache` and `sync::DistanceCache`, `C` is the
+/// type of cache being built.
+///
+/// The defaults match the values the crate has always used, WGS84, exact
+/// keys, a 90 second time-to-idle, 64 initial entries, and at most 65356
+/// entries. Caches are named `"default"` unless told otherwise.
+#[derive(Clone,Debug)]
+pub struct DistanceCacheBuilder<C> {
+    pub(crate) name: String,
+    pub(crate) ellipsoid: Ellipsoid,
+    pub(crate) quantization: Quantization,
+    pub(crate) time_to_live: Option<Duration>,
+    pub(crate) time_to_idle: Option<Duration>,
+    pub(crate) initial_capacity: usize,
+    pub(crate) max_capacity: u64,
+    pub(crate) adaptive: Option<Adaptive>,
+    cache_type: PhantomData<C>,
+}
+impl<C> Default for DistanceCacheBuilder<C> {
+    fn default() -> Self {
+        Self {
+            name: String::from("default"),
+            ellipsoid: Ellipsoid::WGS84,
+            quantization: Quantization::Exact,
+            time_to_live: Option::None,
+            time_to_idle: Option::Some(Duration::from_secs(90)),
+            initial_capacity: 64,
+            max_capacity: 65356,
+            adaptive: Option::None,
+            cache_type: PhantomData,
+        }
+    }
+}
+impl<C> DistanceCacheBuilder<C> {
+    /// Identifies the cache, used as the `cache` label on emitted metrics
+    pub fn name<N: Into<String>>(mut self, name: N) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Model results are computed on, a model that fails
+    /// `Ellipsoid::validate` only produces NaN so nothing is cached for it
+    pub fn ellipsoid(mut self, ellipsoid: Ellipsoid) -> Self {
+        self.ellipsoid = ellipsoid;
+        self
+    }
+
+    /// How positions are snapped before being used as keys, exact by default
+    pub fn quantization(mut self, quantization: Quantization) -> Self {
+        self.quantization = quantization;
+        self
+    }
+
+    /// Entries are evicted this long after they were inserted
+    pub fn time_to_live(mut self, duration: Duration) -> Self {
+        self.time_to_live = Option::Some(duration);
+        self
+    }
+
+    /// Entries are evicted after going unread for this long
+    pub fn time_to_idle(mut self, duration: Duration) -> Self {
+        self.time_to_idle = Option::Some(duration);
+        self
+    }
+
+    /// Disables idle based eviction
+    pub fn no_time_to_idle(mut self) -> Self {
+        self.time_to_idle = Option::None;
+        self
+    }
+
+    /// Number of entries to pre-allocate space for, in each of the
+    /// distance, geodesic and destination tables
+    pub fn initial_capacity(mut self, number_of_entries: usize) -> Self {
+        self.initial_capacity = number_of_entries;
+        self
+    }
+
+    /// Upper bound on the number of entries retained by each of the
+    /// distance, geodesic and destination tables.
+    ///
+    /// The limit is per table, so a cache holding all three kinds of
+    /// result can hold up to three times `max_capacity` entries in total.
+    pub fn max_capacity(mut self, max_capacity: u64) -> Self {
+        self.max_capacity = max_capacity;
+        self
+    }
+
+    /// Skip the cache for `distance` while measurements say it is slower
+    /// than solving, see `Adaptive`
+    pub fn adaptive(mut self, adaptive: Adaptive) -> Self {
+        self.adaptive = Option::Some(adaptive);
+        self
+    }
+}
@@ -0,0 +1,379 @@
+//! Everything `future::DistanceCache` and `sync::DistanceCache` share.
+//!
+//! Validation, key building, restoring results to the caller's
+//! orientation, stats, the adaptive bypass, batches and snapshots all live
+//! here. The two cache types only add the moka calls, which differ in
+//! whether they take a closure or a future, so a fix made here reaches both.
+//!
+//! Each operation is split into `begin_*`, which either answers the call
+//! outright (invalid input, bypassing) or returns the key to look up, the
+//! `solve_*` used to fill a miss, and `end_*` which turns the cached value
+//! into the caller's result.
+
+use std::{
+    collections::{HashMap},
+    io::{Read,Write},
+    sync::{
+        Arc,
+        atomic::{AtomicU64,Ordering},
+    },
+    time::{Duration,Instant},
+};
+
+use geographiclib_rs::{Geodesic};
+
+use crate::{
+    Destination,DistanceCacheBuilder,DistanceData,Ellipsoid,GeodesicData,IntoPosition,Position,Quantization,SnapshotError,
+    solve_distance,
+    adaptive::{Bypass,Probe},
+    direct::{solve_destination},
+    inverse::{solve_geodesic},
+    key::{DestinationKey,Orientation,PairKey,is_directional,pair_key},
+    snapshot::{Snapshot},
+    stats::{CacheStats,Counters,Evictions,time_fn},
+    trace::{LookupSpan},
+};
+
+/// Configuration & bookkeeping of one `DistanceCache`, minus the storage.
+pub(crate) struct Core {
+    name: String,
+    ellipsoid: Ellipsoid,
+    quantization: Quantization,
+    geodesic: Geodesic,
+    c2: f64,
+    counters: Counters,
+    bypass: Bypass,
+}
+
+/// A `distance` lookup in progress
+pub(crate) struct DistanceLookup {
+    pub(crate) key: PairKey,
+    pub(crate) span: LookupSpan,
+    orient: Orientation,
+    started: Instant,
+    probe: Probe,
+    /// Nanoseconds this call spent solving plus one, zero unless it solved
+    solved: AtomicU64,
+}
+
+/// A `geodesic` or `destination` lookup in progress
+pub(crate) struct Lookup<K,O> {
+    pub(crate) key: K,
+    orient: O,
+    started: Instant,
+}
+
+impl Core {
+    pub(crate) fn new<C>(config: &DistanceCacheBuilder<C>) -> Self {
+        Self {
+            name: config.name.clone(),
+            ellipsoid: config.ellipsoid,
+            quantization: config.quantization,
+            geodesic: config.ellipsoid.geodesic(),
+            c2: config.ellipsoid.authalic_radius_squared(),
+            counters: Counters::new(&config.name),
+            bypass: Bypass::new(config.adaptive),
+        }
+    }
+
+    /// Handed to the eviction listener of every underlying cache
+    pub(crate) fn evictions(&self) -> Evictions {
+        self.counters.evictions()
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn ellipsoid(&self) -> Ellipsoid {
+        self.ellipsoid
+    }
+
+    pub(crate) fn quantization(&self) -> Quantization {
+        self.quantization
+    }
+
+    /// Authalic radius squared, what `GeodesicData::area` is scaled by
+    pub(crate) fn c2(&self) -> f64 {
+        self.c2
+    }
+
+    pub(crate) fn solved(&self) -> u64 {
+        self.counters.misses()
+    }
+
+    pub(crate) fn stats(&self, entry_count: u64) -> CacheStats {
+        self.counters.snapshot(entry_count)
+    }
+
+    pub(crate) fn bypassing(&self) -> bool {
+        self.bypass.is_bypassing()
+    }
+
+    /// Key for a pair, unless the pair can't be cached
+    fn pair(&self, a_pos: Position, b_pos: Position) -> Option<(PairKey,Orientation)> {
+        if !(self.ellipsoid.is_valid() && a_pos.is_valid() && b_pos.is_valid()) {
+            return Option::None;
+        }
+        // checked again after quantizing, NaN must never be cached, and a
+        // pair that depends on its direction can't share its reverse's key
+        let (key, orient) = pair_key(self.ellipsoid, &self.quantization, a_pos, b_pos);
+        if key.1.is_valid() && key.2.is_valid() && !is_directional(key.1, key.2) {
+            Option::Some((key, orient))
+        } else {
+            Option::None
+        }
+    }
+
+    pub(crate) fn begin_distance<A,B>(&self, a: &A, b: &B) -> Result<DistanceLookup,DistanceData>
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        let a_pos: Position = a.into_position();
+        let b_pos: Position = b.into_position();
+        let (key, orient) = match self.pair(a_pos, b_pos) {
+            Option::Some(pair) => pair,
+            // garbage in, garbage out, but never cached
+            Option::None => return Err(solve_distance(&self.geodesic, a_pos, b_pos)),
+        };
+        if self.bypass.should_bypass() {
+            self.counters.record_bypass();
+            // the snapped positions as given, what the cache would have held
+            return Err(solve_distance(&self.geodesic, self.quantization.quantize(&a_pos), self.quantization.quantize(&b_pos)));
+        }
+        self.counters.record_lookups(1);
+        Ok(DistanceLookup {
+            key,
+            span: LookupSpan::new("distance", &self.name, orient.flipped()),
+            orient,
+            started: Instant::now(),
+            probe: self.bypass.begin_lookup(),
+            solved: AtomicU64::new(0),
+        })
+    }
+
+    /// Fill a `distance` miss
+    pub(crate) fn solve_distance(&self, lookup: &DistanceLookup) -> DistanceData {
+        self.bypass.begin_solve();
+        let (solved, took) = self.solve_pair(&lookup.key);
+        lookup.span.record_miss(took);
+        lookup.solved.store(took.as_nanos() as u64 + 1, Ordering::Relaxed);
+        self.bypass.record_solve(took);
+        solved
+    }
+
+    pub(crate) fn end_distance(&self, lookup: DistanceLookup, mut dist: DistanceData) -> DistanceData {
+        let took = lookup.started.elapsed();
+        self.counters.record_lookup_time(took);
+        let solved = match lookup.solved.load(Ordering::Relaxed) {
+            0 => Option::None,
+            nanos => Option::Some(Duration::from_nanos(nanos - 1)),
+        };
+        self.bypass.record_lookup(lookup.probe, took, solved);
+        dist.restore(&lookup.orient);
+        dist
+    }
+
+    pub(crate) fn begin_geodesic<A,B>(&self, a: &A, b: &B) -> Result<Lookup<PairKey,Orientation>,GeodesicData>
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        let a_pos: Position = a.into_position();
+        let b_pos: Position = b.into_position();
+        let (key, orient) = match self.pair(a_pos, b_pos) {
+            Option::Some(pair) => pair,
+            Option::None => return Err(solve_geodesic(&self.geodesic, a_pos, b_pos)),
+        };
+        self.counters.record_lookups(1);
+        Ok(Lookup { key, orient, started: Instant::now() })
+    }
+
+    /// Fill a `geodesic` miss
+    pub(crate) fn solve_geodesic(&self, key: &PairKey) -> GeodesicData {
+        let (solved, took) = time_fn(|| solve_geodesic(&self.geodesic, key.1, key.2));
+        self.record_miss(took);
+        solved
+    }
+
+    pub(crate) fn end_geodesic(&self, lookup: Lookup<PairKey,Orientation>, mut data: GeodesicData) -> GeodesicData {
+        self.counters.record_lookup_time(lookup.started.elapsed());
+        data.restore(&lookup.orient, self.c2());
+        data
+    }
+
+    pub(crate) fn begin_destination<A>(&self, start: &A, azimuth: f64, distance: f64) -> Result<Lookup<DestinationKey,()>,Destination>
+    where
+        A: IntoPosition,
+    {
+        let start: Position = start.into_position();
+        if !(self.ellipsoid.is_valid() && start.is_valid() && azimuth.is_finite() && distance.is_finite()) {
+            return Err(solve_destination(&self.geodesic, start, azimuth, distance));
+        }
+        let key = DestinationKey::new(self.ellipsoid, &self.quantization, start, azimuth, distance);
+        if !key.start.is_valid() {
+            return Err(solve_destination(&self.geodesic, start, azimuth, distance));
+        }
+        self.counters.record_lookups(1);
+        Ok(Lookup { key, orient: (), started: Instant::now() })
+    }
+
+    /// Fill a `destination` miss
+    pub(crate) fn solve_destination(&self, key: &DestinationKey) -> Destination {
+        let (solved, took) = time_fn(|| solve_destination(&self.geodesic, key.start, key.azimuth, key.distance));
+        self.record_miss(took);
+        solved
+    }
+
+    pub(crate) fn end_destination(&self, lookup: Lookup<DestinationKey,()>, dest: Destination) -> Destination {
+        self.counters.record_lookup_time(lookup.started.elapsed());
+        dest
+    }
+
+    /// Key every pair of a batch, pairs that can't be cached are solved
+    /// straight away
+    pub(crate) fn begin_batch(&self, pairs: Vec<(Position,Position)>) -> Batch {
+        let mut batch = Batch::with_capacity(pairs.len());
+        for (a_pos, b_pos) in pairs {
+            match self.pair(a_pos, b_pos) {
+                Option::Some((key, orient)) => {
+                    self.counters.record_lookups(1);
+                    batch.push(key, orient);
+                },
+                Option::None => batch.push_solved(solve_distance(&self.geodesic, a_pos, b_pos)),
+            };
+        }
+        batch
+    }
+
+    /// Fill a batch miss, each key goes through `get_with` on its own so a
+    /// key another caller is already solving is waited for, not solved again
+    pub(crate) fn solve_batch_key(&self, key: &PairKey) -> DistanceData {
+        self.solve_pair(key).0
+    }
+
+    /// Solve a key, counting it as a miss that was inserted
+    fn solve_pair(&self, key: &PairKey) -> (DistanceData,Duration) {
+        let (solved, took) = time_fn(|| solve_distance(&self.geodesic, key.1, key.2));
+        self.record_miss(took);
+        (solved, took)
+    }
+
+    fn record_miss(&self, took: Duration) {
+        self.counters.record_misses(1, took);
+        self.counters.record_insertions(1);
+    }
+
+    /// Write the entries of all three caches as a snapshot, returns the
+    /// number of entries written
+    pub(crate) fn write_snapshot<W,D,G,T>(&self, w: &mut W, distances: D, geodesics: G, destinations: T) -> Result<u64,SnapshotError>
+    where
+        W: Write,
+        D: Iterator<Item = (Arc<PairKey>,DistanceData)>,
+        G: Iterator<Item = (Arc<PairKey>,GeodesicData)>,
+        T: Iterator<Item = (Arc<DestinationKey>,Destination)>,
+    {
+        let snapshot = Snapshot {
+            distances: distances.map(|(key, value)| (*key, value)).collect(),
+            geodesics: geodesics.map(|(key, value)| (*key, value)).collect(),
+            destinations: destinations.map(|(key, value)| (*key, value)).collect(),
+        };
+        snapshot.write_to(w, self.ellipsoid, &self.quantization)?;
+        Ok(snapshot.len())
+    }
+
+    /// Read a snapshot for this cache, its entries are counted as inserted
+    pub(crate) fn read_snapshot<R: Read>(&self, r: &mut R) -> Result<Snapshot,SnapshotError> {
+        let snapshot = Snapshot::read_from(r, self.ellipsoid, &self.quantization)?;
+        self.counters.record_insertions(snapshot.len());
+        Ok(snapshot)
+    }
+}
+
+/// The pairs of a batch query, in the caller's order.
+///
+/// Keys are deduplicated, so a pair that appears several times in one
+/// batch (A->B & B->A in a symmetric matrix) is only looked up once, and
+/// solved at most once.
+pub(crate) struct Batch {
+    out: Vec<DistanceData>,
+    index: HashMap<PairKey,usize>,
+    keys: Vec<PairKey>,
+    waiting: Vec<(usize,usize,Orientation)>,
+}
+impl Batch {
+    fn with_capacity(len: usize) -> Self {
+        Self {
+            out: Vec::with_capacity(len),
+            index: HashMap::new(),
+            keys: Vec::new(),
+            waiting: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, key: PairKey, orient: Orientation) {
+        let keys = &mut self.keys;
+        let id = *self.index.entry(key).or_insert_with(|| {
+            keys.push(key);
+            keys.len() - 1
+        });
+        self.waiting.push((self.out.len(), id, orient));
+        // placeholder, filled in by `finish`
+        self.out.push(DistanceData { distance: f64::NAN, forward_azimuth: f64::NAN, backward_azimuth: f64::NAN });
+    }
+
+    fn push_solved(&mut self, dist: DistanceData) {
+        self.out.push(dist);
+    }
+
+    /// Every distinct key to look up
+    pub(crate) fn keys(&self) -> &[PairKey] {
+        &self.keys
+    }
+
+    /// Restore the value of each key (in `keys` order) into every slot
+    /// waiting on it
+    pub(crate) fn finish(self, values: &[DistanceData]) -> Vec<DistanceData> {
+        let mut out = self.out;
+        for (slot, id, orient) in self.waiting {
+            let mut dist = values[id];
+            dist.restore(&orient);
+            out[slot] = dist;
+        }
+        out
+    }
+}
+
+/// The pairs of `distance_matrix`, row major
+pub(crate) fn matrix_pairs<A,B>(origins: &[A], destinations: &[B]) -> Vec<(Position,Position)>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    let destinations: Vec<Position> = destinations.iter().map(IntoPosition::into_position).collect();
+    origins.iter()
+        .flat_map(|a| {
+            let a_pos = a.into_position();
+            destinations.iter().map(move |b_pos| (a_pos, *b_pos))
+        })
+        .collect()
+}
+
+/// The pairs of `distances_from`
+pub(crate) fn from_pairs<A,B>(origin: &A, destinations: &[B]) -> Vec<(Position,Position)>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    let a_pos = origin.into_position();
+    destinations.iter().map(|b| (a_pos, b.into_position())).collect()
+}
+
+/// The segments of `path_length`
+pub(crate) fn path_pairs<A>(points: &[A]) -> Vec<(Position,Position)>
+where
+    A: IntoPosition,
+{
+    points.windows(2).map(|seg| (seg[0].into_position(), seg[1].into_position())).collect()
+}
@@ -0,0 +1,44 @@
+use geographiclib_rs::{Geodesic};
+
+use crate::{Ellipsoid,IntoPosition,Position,normalize_azimuth};
+
+/// Result of travelling along a geodesic from a known position.
+#[derive(Copy,Clone,PartialEq,PartialOrd,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Destination {
+    /// Where you end up
+    pub position: Position,
+    /// Bearing you'd have on arrival, measured in the direction of travel
+    pub azimuth: f64,
+}
+
+/// Find where you end up after travelling `distance` meters from `start`
+/// with an initial bearing of `azimuth` degrees on WGS84.
+pub fn uncached_destination<A>(start: &A, azimuth: f64, distance: f64) -> Destination
+where
+    A: IntoPosition,
+{
+    uncached_destination_on(&Ellipsoid::WGS84, start, azimuth, distance)
+}
+
+/// Find where you end up after travelling `distance` meters from `start`
+/// with an initial bearing of `azimuth` degrees on the supplied ellipsoid.
+///
+/// See `uncached_distance_on` for the cost of non-WGS84 ellipsoids.
+pub fn uncached_destination_on<A>(ellipsoid: &Ellipsoid, start: &A, azimuth: f64, distance: f64) -> Destination
+where
+    A: IntoPosition,
+{
+    ellipsoid.with_geodesic(|geod| solve_destination(geod, start.into_position(), azimuth, distance))
+}
+
+pub(crate) fn solve_destination(geod: &Geodesic, start: Position, azimuth: f64, distance: f64) -> Destination {
+    use geographiclib_rs::{DirectGeodesic};
+
+    let (lat2, lon2, azi2): (f64,f64,f64) = geod.direct(start.get_lat(), start.get_lon(), azimuth, distance);
+
+    Destination {
+        position: Position::new(lat2, lon2),
+        azimuth: normalize_azimuth(azi2),
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    hash::{Hash,Hasher},
+};
+
+use geographiclib_rs::{Geodesic};
+
+use crate::{EllipsoidError};
+
+lazy_static! {
+    /// Solvers for the bundled models, WGS84 first as it is the default
+    static ref BUNDLED_GEODESICS: Vec<(Ellipsoid,Geodesic)> = [
+        Ellipsoid::WGS84,
+        Ellipsoid::GRS80,
+        Ellipsoid::CLARKE_1866,
+        Ellipsoid::SPHERE,
+        Ellipsoid::MOON,
+        Ellipsoid::MARS,
+    ].iter().map(|ellipsoid| (*ellipsoid, ellipsoid.geodesic())).collect();
+}
+
+/// The model of the body distances are measured on.
+///
+/// Defined by its equatorial radius in meters and its flattening. A
+/// flattening of zero describes a sphere. Like `Position` the values are
+/// compared by their bit patterns so an ellipsoid can be part of a cache
+/// key, results computed on different models never collide.
+#[derive(Clone,Copy,Debug)]
+pub struct Ellipsoid {
+    equatorial_radius: f64,
+    flattening: f64,
+}
+impl PartialEq for Ellipsoid {
+    fn eq(&self, other: &Self) -> bool {
+        (self.equatorial_radius.to_ne_bytes() == other.equatorial_radius.to_ne_bytes())
+            &
+        (self.flattening.to_ne_bytes() == other.flattening.to_ne_bytes())
+    }
+}
+impl Eq for Ellipsoid { }
+impl Hash for Ellipsoid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write( self.equatorial_radius.to_ne_bytes().as_ref());
+        state.write( self.flattening.to_ne_bytes().as_ref());
+    }
+}
+impl Default for Ellipsoid {
+    fn default() -> Self {
+        Self::WGS84
+    }
+}
+impl Ellipsoid {
+    /// World Geodetic System 1984, used by GPS
+    ///
+    /// The flattening is written the same way geographiclib writes it so
+    /// the two agree to the bit.
+    pub const WGS84: Ellipsoid = Ellipsoid::new(6378137.0, 1.0 / (298257223563.0 / 1000000000.0));
+    /// Geodetic Reference System 1980, used by NAD83 & ETRS89
+    pub const GRS80: Ellipsoid = Ellipsoid::new(6378137.0, 1.0 / 298.257222101);
+    /// Clarke 1866, used by NAD27
+    pub const CLARKE_1866: Ellipsoid = Ellipsoid::new(6378206.4, 1.0 / 294.978698214);
+    /// Sphere with the IUGG mean radius of the earth
+    pub const SPHERE: Ellipsoid = Ellipsoid::new(6371008.8, 0.0);
+    /// The Moon, IAU 2015 mean radius
+    pub const MOON: Ellipsoid = Ellipsoid::new(1737400.0, 0.0);
+    /// Mars, IAU 2015 equatorial radius and flattening
+    pub const MARS: Ellipsoid = Ellipsoid::new(3396190.0, 1.0 / 169.894447223612);
+
+    /// Build a model without checking it, every result computed on a
+    /// model that fails `validate` is NaN and is never cached
+    pub const fn new(equatorial_radius: f64, flattening: f64) -> Self {
+        Self { equatorial_radius, flattening }
+    }
+
+    /// Build a model, rejecting a radius that is not finite and positive
+    /// or a flattening that is not finite and below 1.
+    pub fn try_new(equatorial_radius: f64, flattening: f64) -> Result<Self,EllipsoidError> {
+        Self::new(equatorial_radius, flattening).validate()
+    }
+
+    /// Check the model is something the solver can work with
+    pub fn validate(self) -> Result<Self,EllipsoidError> {
+        if !(self.equatorial_radius.is_finite() && self.equatorial_radius > 0.0) {
+            return Err(EllipsoidError::EquatorialRadius(self.equatorial_radius));
+        }
+        if !(self.flattening.is_finite() && self.flattening < 1.0) {
+            return Err(EllipsoidError::Flattening(self.flattening));
+        }
+        Ok(self)
+    }
+
+    /// `validate` without building the error
+    #[cfg(any(feature = "async", feature = "sync"))]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Radius at the equator in meters
+    pub fn equatorial_radius(&self) -> f64 { self.equatorial_radius }
+
+    /// Flattening, `(a - b) / a`
+    pub fn flattening(&self) -> f64 { self.flattening }
+
+    /// Square of the authalic radius, the radius of the sphere with the same
+    /// surface area. `GeodesicData::area` moves by this much per radian and
+    /// the whole ellipsoid covers `4 PI` of it.
+    pub(crate) fn authalic_radius_squared(&self) -> f64 {
+        let a = self.equatorial_radius;
+        let b = a * (1.0 - self.flattening);
+        let e2 = self.flattening * (2.0 - self.flattening);
+        let ratio = if e2 == 0.0 {
+            1.0
+        } else if e2 > 0.0 {
+            e2.sqrt().atanh() / e2.sqrt()
+        } else {
+            (-e2).sqrt().atan() / (-e2).sqrt()
+        };
+        (a * a + b * b * ratio) / 2.0
+    }
+
+    /// Build the solver for this model.
+    ///
+    /// This computes the series coefficients from scratch, hold onto the
+    /// result rather than calling it per query.
+    pub(crate) fn geodesic(&self) -> Geodesic {
+        Geodesic::new(self.equatorial_radius, self.flattening)
+    }
+
+    /// Run `func` with the solver for this model, the bundled constants
+    /// share precomputed solvers and every other model builds a new one.
+    pub(crate) fn with_geodesic<R,F>(&self, func: F) -> R
+    where
+        F: FnOnce(&Geodesic) -> R,
+    {
+        match BUNDLED_GEODESICS.iter().find(|(ellipsoid, _)| ellipsoid == self) {
+            Option::Some((_, geod)) => func(geod),
+            Option::None => func(&self.geodesic()),
+        }
+    }
+}
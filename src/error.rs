@@ -0,0 +1,93 @@
+use std::{
+    fmt,
+    io,
+    error::{Error},
+};
+
+use crate::{Ellipsoid,Quantization};
+
+/// Why a coordinate was rejected.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PositionError {
+    /// Latitude or longitude was NaN
+    NaN,
+    /// Latitude was outside of [-90, 90], the value is included
+    LatitudeOutOfRange(f64),
+    /// Longitude was infinite, the value is included
+    NonFiniteLongitude(f64),
+}
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionError::NaN => write!(f, "coordinate is NaN"),
+            PositionError::LatitudeOutOfRange(lat) => write!(f, "latitude {} is outside of [-90, 90]", lat),
+            PositionError::NonFiniteLongitude(lon) => write!(f, "longitude {} is not finite", lon),
+        }
+    }
+}
+impl Error for PositionError { }
+
+/// Why an ellipsoid was rejected.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum EllipsoidError {
+    /// Equatorial radius was not finite and positive, the value is included
+    EquatorialRadius(f64),
+    /// Flattening was not finite and below 1, the value is included
+    Flattening(f64),
+}
+impl fmt::Display for EllipsoidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EllipsoidError::EquatorialRadius(radius) => write!(f, "equatorial radius {} is not finite and positive", radius),
+            EllipsoidError::Flattening(flattening) => write!(f, "flattening {} is not finite and below 1", flattening),
+        }
+    }
+}
+impl Error for EllipsoidError { }
+
+/// Why a snapshot could not be written or loaded.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading or writing the file failed
+    Io(io::Error),
+    /// The file does not start with the snapshot magic bytes
+    NotASnapshot,
+    /// The snapshot format is newer (or older) than this build understands
+    UnsupportedFormat(u16),
+    /// The snapshot was written by another version of this crate, which
+    /// may solve geodesics differently, the version is included
+    CrateVersion(String),
+    /// The snapshot was computed on a different ellipsoid, it is included
+    EllipsoidMismatch(Ellipsoid),
+    /// The snapshot's keys were quantized differently, its mode is included
+    QuantizationMismatch(Quantization),
+    /// The header could not be decoded, or an entry's key is one the
+    /// cache would never hold (NaN, out of range, or mirrored latitudes)
+    Corrupt,
+}
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "snapshot i/o failed: {}", err),
+            SnapshotError::NotASnapshot => write!(f, "file is not a distance cache snapshot"),
+            SnapshotError::UnsupportedFormat(format) => write!(f, "snapshot format {} is not supported", format),
+            SnapshotError::CrateVersion(version) => write!(f, "snapshot was written by version {}", version),
+            SnapshotError::EllipsoidMismatch(ellipsoid) => write!(f, "snapshot was computed on {:?}", ellipsoid),
+            SnapshotError::QuantizationMismatch(quantization) => write!(f, "snapshot keys were quantized with {:?}", quantization),
+            SnapshotError::Corrupt => write!(f, "snapshot is corrupt"),
+        }
+    }
+}
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Option::Some(err),
+            _ => Option::None,
+        }
+    }
+}
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
@@ -0,0 +1,36 @@
+//! Conversions between `geo_types` and this crate.
+//!
+//! `geo_types` stores `x` as longitude and `y` as latitude, the opposite
+//! order to `Position::new(lat, lon)`.
+
+use geo_types::{Coord,CoordNum,Point};
+
+use crate::{IntoPosition,Position};
+
+impl<T> IntoPosition for Coord<T>
+where
+    T: CoordNum + Into<f64>,
+{
+    fn get_lat(&self) -> f64 { self.y.into() }
+    fn get_lon(&self) -> f64 { self.x.into() }
+}
+
+impl<T> IntoPosition for Point<T>
+where
+    T: CoordNum + Into<f64>,
+{
+    fn get_lat(&self) -> f64 { self.y().into() }
+    fn get_lon(&self) -> f64 { self.x().into() }
+}
+
+impl From<Position> for Coord<f64> {
+    fn from(pos: Position) -> Self {
+        Coord { x: pos.get_lon(), y: pos.get_lat() }
+    }
+}
+
+impl From<Position> for Point<f64> {
+    fn from(pos: Position) -> Self {
+        Point::new(pos.get_lon(), pos.get_lat())
+    }
+}
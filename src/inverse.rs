@@ -0,0 +1,110 @@
+use geographiclib_rs::{Geodesic};
+
+use crate::{DistanceData,Ellipsoid,IntoPosition,Position,normalize_azimuth};
+
+/// Every output of the inverse geodesic problem.
+///
+/// `DistanceData` covers the common case, this is for callers who need
+/// to estimate errors or accumulate polygon areas. Solving for these
+/// extra values is more expensive so they are cached seperately from
+/// `DistanceData`.
+#[derive(Copy,Clone,PartialEq,PartialOrd,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeodesicData {
+    /// Distance from `A` to `B` in meters
+    pub distance: f64,
+    /// Bearing you'd have to have to reach `B` from `A`
+    pub forward_azimuth: f64,
+    /// Bearing you'd have on arrival at `B`, measured in the direction of travel
+    pub backward_azimuth: f64,
+    /// Arc length from `A` to `B` on the auxiliary sphere in degrees
+    pub arc_length: f64,
+    /// Reduced length of the geodesic in meters
+    pub reduced_length: f64,
+    /// Geodesic scale of `B` relative to `A`
+    pub geodesic_scale_ab: f64,
+    /// Geodesic scale of `A` relative to `B`
+    pub geodesic_scale_ba: f64,
+    /// Area between the geodesic and the equator in square meters
+    pub area: f64,
+}
+impl GeodesicData {
+    /// Swap `A` and `B`, see `DistanceData::reverse`.
+    ///
+    /// Distance, arc length and reduced length are symmetric, the
+    /// geodesic scales trade places, and the area changes sign. The one
+    /// exception is a geodesic over a pole (longitudes exactly 180 apart),
+    /// GeographicLib measures both directions of those as heading east so
+    /// the area is the same either way.
+    #[cfg(any(feature = "async", feature = "sync"))]
+    pub(crate) fn reverse(&mut self, should_reverse: bool) {
+        use std::mem::{swap};
+
+        if should_reverse {
+            let over_pole = (self.forward_azimuth == 0.0 && self.backward_azimuth == 180.0)
+                || (self.forward_azimuth == 180.0 && self.backward_azimuth == 0.0);
+            if !over_pole {
+                self.area = -self.area;
+            }
+            swap(&mut self.forward_azimuth, &mut self.backward_azimuth);
+            self.forward_azimuth = normalize_azimuth(self.forward_azimuth + 180.0);
+            self.backward_azimuth = normalize_azimuth(self.backward_azimuth + 180.0);
+            swap(&mut self.geodesic_scale_ab, &mut self.geodesic_scale_ba);
+        }
+    }
+}
+impl From<GeodesicData> for DistanceData {
+    fn from(data: GeodesicData) -> DistanceData {
+        DistanceData {
+            distance: data.distance,
+            forward_azimuth: data.forward_azimuth,
+            backward_azimuth: data.backward_azimuth,
+        }
+    }
+}
+
+/// Solve the inverse problem between 2 points on WGS84, returning every output
+pub fn uncached_geodesic<A,B>(a: &A, b: &B) -> GeodesicData
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    uncached_geodesic_on(&Ellipsoid::WGS84, a, b)
+}
+
+/// Solve the inverse problem between 2 points on the supplied ellipsoid
+///
+/// See `uncached_distance_on` for the cost of non-WGS84 ellipsoids.
+pub fn uncached_geodesic_on<A,B>(ellipsoid: &Ellipsoid, a: &A, b: &B) -> GeodesicData
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    ellipsoid.with_geodesic(|geod| solve_geodesic(geod, a.into_position(), b.into_position()))
+}
+
+/// Solve from `a_pos` to `b_pos` exactly as given, the caches order their
+/// keys themselves.
+///
+/// For mirrored latitudes (pole to pole, or a point to its antipode)
+/// GeographicLib picks between equally short geodesics based on the
+/// direction it is asked for, so reversing the opposite direction's
+/// result is not the same geodesic.
+pub(crate) fn solve_geodesic(geod: &Geodesic, a_pos: Position, b_pos: Position) -> GeodesicData {
+    use geographiclib_rs::{InverseGeodesic};
+
+    #[allow(non_snake_case)]
+    let (s12, azi1, azi2, m12, M12, M21, S12, a12): (f64,f64,f64,f64,f64,f64,f64,f64) =
+        geod.inverse(a_pos.get_lat(), a_pos.get_lon(), b_pos.get_lat(), b_pos.get_lon());
+
+    GeodesicData {
+        distance: s12,
+        forward_azimuth: normalize_azimuth(azi1),
+        backward_azimuth: normalize_azimuth(azi2),
+        arc_length: a12,
+        reduced_length: m12,
+        geodesic_scale_ab: M12,
+        geodesic_scale_ba: M21,
+        area: S12,
+    }
+}
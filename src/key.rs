@@ -0,0 +1,156 @@
+//! Cache keys shared by `future::DistanceCache` and `sync::DistanceCache`.
+//!
+//! Every key is built from quantized, canonical positions (see
+//! `Quantization` and `Position::canonical`) so equivalent inputs share an
+//! entry. Results are computed for the key and then restored to what the
+//! caller asked for.
+
+use std::{
+    hash::{Hash,Hasher},
+};
+
+use crate::{DistanceData,Ellipsoid,GeodesicData,IntoPosition,Position,Quantization,lon_diff,normalize_azimuth};
+
+/// Key used to store a pair of positions, southern most point first.
+///
+/// The ellipsoid is part of the key so results computed on different
+/// models never collide.
+pub(crate) type PairKey = (Ellipsoid,Position,Position);
+
+/// How a cached pair relates to the pair the caller asked for.
+#[derive(Clone,Copy,Debug)]
+pub(crate) struct Orientation {
+    flip: bool,
+    a_offset: f64,
+    b_offset: f64,
+    area_shift: f64,
+}
+
+impl Orientation {
+    /// the caller's pair was swapped to build the key
+    pub(crate) fn flipped(&self) -> bool {
+        self.flip
+    }
+}
+
+/// Orders the canonical pair so (A->B & B->A) share a cache entry,
+/// returning how to restore results to the caller's orientation.
+pub(crate) fn pair_key(ellipsoid: Ellipsoid, quantization: &Quantization, a_pos: Position, b_pos: Position) -> (PairKey,Orientation) {
+    let (a_quant, b_quant) = (quantization.quantize(&a_pos), quantization.quantize(&b_pos));
+    let (a_pos, a_offset) = a_quant.canonical_with_offset();
+    let (b_pos, b_offset) = b_quant.canonical_with_offset();
+    let flip: bool = a_pos > b_pos;
+    let area_shift = area_shift((a_quant, b_quant), (a_pos, b_pos));
+    let orient = Orientation { flip, a_offset, b_offset, area_shift };
+    if flip {
+        ((ellipsoid, b_pos, a_pos), orient)
+    } else {
+        ((ellipsoid, a_pos, b_pos), orient)
+    }
+}
+
+/// The geodesic from `a` to `b` is not the reverse of the one from `b` to
+/// `a`, so the pair can't share a key with its reverse.
+///
+/// When the latitudes mirror each other across the equator the shortest
+/// path can run either way around (pole to pole, or a point to its
+/// antipode) and GeographicLib picks one based on the direction it is
+/// solved in.
+pub(crate) fn is_directional(a: Position, b: Position) -> bool {
+    a.get_lat() != 0.0 && a.get_lat() == -b.get_lat()
+}
+
+/// How far, in degrees of longitude, the geodesic's area moves when a
+/// pole's longitude is replaced by 0.
+///
+/// The area grows by `c2` per radian of `lon12` at a north pole and shrinks
+/// at a south pole. GeographicLib reduces `lon12` onto [-180,180] so this
+/// is taken from `lon12` itself, the offsets only agree with it modulo 360.
+/// With both ends at a pole the southern most decides, the key is solved
+/// from it.
+fn area_shift(given: (Position,Position), canonical: (Position,Position)) -> f64 {
+    let pole = |pos: &Position| pos.get_lat().abs() == 90.0;
+    let shift = lon_diff(given.0.get_lon(), given.1.get_lon()) - lon_diff(canonical.0.get_lon(), canonical.1.get_lon());
+    match (pole(&canonical.0), pole(&canonical.1)) {
+        (false, false) => 0.0,
+        (true, true) => shift * canonical.0.get_lat().min(canonical.1.get_lat()).signum(),
+        (true, false) => shift * canonical.0.get_lat().signum(),
+        (false, true) => shift * canonical.1.get_lat().signum(),
+    }
+}
+
+impl DistanceData {
+    /// Turn a result for the cached pair into one for the caller's pair
+    pub(crate) fn restore(&mut self, orient: &Orientation) {
+        self.reverse(orient.flip);
+        if orient.a_offset != 0.0 {
+            self.forward_azimuth = normalize_azimuth(self.forward_azimuth + orient.a_offset);
+        }
+        if orient.b_offset != 0.0 {
+            self.backward_azimuth = normalize_azimuth(self.backward_azimuth + orient.b_offset);
+        }
+    }
+}
+
+impl GeodesicData {
+    /// Turn a result for the cached pair into one for the caller's pair.
+    ///
+    /// The area depends on the angle between the azimuths, so it moves by
+    /// `c2` (the authalic radius squared) per radian the azimuths are rotated.
+    pub(crate) fn restore(&mut self, orient: &Orientation, c2: f64) {
+        self.reverse(orient.flip);
+        if orient.a_offset != 0.0 || orient.b_offset != 0.0 {
+            self.forward_azimuth = normalize_azimuth(self.forward_azimuth + orient.a_offset);
+            self.backward_azimuth = normalize_azimuth(self.backward_azimuth + orient.b_offset);
+        }
+        if orient.area_shift != 0.0 {
+            self.area += c2 * orient.area_shift.to_radians();
+        }
+    }
+}
+
+/// Key for the destination cache.
+///
+/// Like `Position` the floating point values are compared by their bit
+/// patterns so they can be hashed.
+#[derive(Clone,Copy,Debug)]
+pub(crate) struct DestinationKey {
+    pub(crate) ellipsoid: Ellipsoid,
+    pub(crate) start: Position,
+    pub(crate) azimuth: f64,
+    pub(crate) distance: f64,
+}
+impl DestinationKey {
+    /// Quantized canonical start, with the azimuth rotated to match it and
+    /// normalised onto (-180,180]. Azimuth & distance are not quantized.
+    pub(crate) fn new(ellipsoid: Ellipsoid, quantization: &Quantization, start: Position, azimuth: f64, distance: f64) -> Self {
+        let (start, offset) = quantization.quantize(&start).canonical_with_offset();
+        let azimuth = normalize_azimuth(azimuth - offset);
+        Self {
+            ellipsoid,
+            start,
+            azimuth: if azimuth == 0.0 { 0.0 } else { azimuth },
+            distance: if distance == 0.0 { 0.0 } else { distance },
+        }
+    }
+}
+impl PartialEq for DestinationKey {
+    fn eq(&self, other: &Self) -> bool {
+        (self.ellipsoid == other.ellipsoid)
+            &
+        (self.start == other.start)
+            &
+        (self.azimuth.to_ne_bytes() == other.azimuth.to_ne_bytes())
+            &
+        (self.distance.to_ne_bytes() == other.distance.to_ne_bytes())
+    }
+}
+impl Eq for DestinationKey { }
+impl Hash for DestinationKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ellipsoid.hash(state);
+        self.start.hash(state);
+        state.write( self.azimuth.to_ne_bytes().as_ref());
+        state.write( self.distance.to_ne_bytes().as_ref());
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    cmp::{Ordering},
+    collections::{BinaryHeap},
+};
+
+use crate::{DistanceData,Ellipsoid,IntoPosition,Position,solve_distance};
+
+/// One result of `NearestIndex::nearest`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Neighbour<'a,T> {
+    /// The item as it was added to the index
+    pub item: &'a T,
+    /// Where the item sits in `NearestIndex::items`
+    pub index: usize,
+    /// Geodesic from the query to the item on the index's ellipsoid
+    pub distance: DistanceData,
+}
+
+/// Answers "the `k` closest items to this point" queries.
+///
+/// Ranking uses the exact ellipsoidal distance from `uncached_distance_on`,
+/// but the inverse problem is only solved for a handful of items. Each
+/// item is stored as a point in space, in a k-d tree. The straight line
+/// (chord) through the earth between 2 points is never longer than the
+/// geodesic over its surface, so the tree is walked in chord order and the
+/// search stops once the next chord is longer than the `k`th geodesic found
+/// so far.
+///
+/// Items whose positions fail `Position::validate` are kept in `items` but
+/// never returned. The index is immutable, rebuild it when the items change.
+#[derive(Clone,Debug)]
+pub struct NearestIndex<T> {
+    ellipsoid: Ellipsoid,
+    items: Vec<T>,
+    positions: Vec<Position>,
+    points: Vec<[f64; 3]>,
+    /// Indices of the valid items laid out as an implicit k-d tree, each
+    /// range splits at its middle element on the axis `depth % 3`
+    tree: Vec<usize>,
+}
+impl<T: IntoPosition> NearestIndex<T> {
+    /// Index `items` on WGS84
+    pub fn new<I>(items: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::new_on(Ellipsoid::WGS84, items)
+    }
+
+    /// Index `items` on the supplied ellipsoid
+    pub fn new_on<I>(ellipsoid: Ellipsoid, items: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let items: Vec<T> = items.into_iter().collect();
+        let positions: Vec<Position> = items.iter().map(|item| item.into_position()).collect();
+        let points: Vec<[f64; 3]> = positions.iter().map(|pos| to_cartesian(&ellipsoid, pos)).collect();
+        let mut tree: Vec<usize> = (0..positions.len()).filter(|&i| positions[i].is_valid()).collect();
+        build(&mut tree, &points, 0);
+        Self { ellipsoid, items, positions, points, tree }
+    }
+
+    /// Every item in the order they were added
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// The model distances are computed on
+    pub fn ellipsoid(&self) -> Ellipsoid {
+        self.ellipsoid
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The `k` items closest to `query`, closest first. Ties are broken by
+    /// `index`, fewer than `k` are returned when the index is smaller. An
+    /// invalid `query` has no neighbours.
+    pub fn nearest<P>(&self, query: &P, k: usize) -> Vec<Neighbour<'_,T>>
+    where
+        P: IntoPosition,
+    {
+        let query = query.into_position();
+        if k == 0 || self.tree.is_empty() || !query.is_valid() {
+            return Vec::new();
+        }
+        let origin = to_cartesian(&self.ellipsoid, &query);
+
+        let found = self.ellipsoid.with_geodesic(|geod| {
+            let mut found: Vec<(DistanceData,usize)> = Vec::with_capacity(k + 1);
+            let mut pending = BinaryHeap::new();
+            pending.push(Pending { bound: 0.0, visit: Visit::Range { start: 0, end: self.tree.len(), depth: 0 } });
+            while let Option::Some(Pending { bound, visit }) = pending.pop() {
+                if found.len() == k {
+                    // for nearby points the chord & geodesic only differ by rounding
+                    let worst = found[k - 1].0.distance;
+                    if bound * (1.0 - 1e-12) > worst {
+                        break;
+                    }
+                }
+                match visit {
+                    Visit::Item(index) => {
+                        let data = solve_distance(geod, query, self.positions[index]);
+                        let at = found.partition_point(|(other, other_index)| {
+                            other.distance < data.distance || (other.distance == data.distance && *other_index < index)
+                        });
+                        if at < k {
+                            found.insert(at, (data, index));
+                            found.truncate(k);
+                        }
+                    },
+                    Visit::Range { start, end, depth } => {
+                        let mid = start + (end - start) / 2;
+                        let split = self.tree[mid];
+                        pending.push(Pending { bound: chord(&origin, &self.points[split]), visit: Visit::Item(split) });
+                        // the far side is at least as far as the splitting plane
+                        let offset = origin[depth % 3] - self.points[split][depth % 3];
+                        let (near, far) = if offset < 0.0 {
+                            ((start, mid), (mid + 1, end))
+                        } else {
+                            ((mid + 1, end), (start, mid))
+                        };
+                        if near.0 < near.1 {
+                            pending.push(Pending { bound, visit: Visit::Range { start: near.0, end: near.1, depth: depth + 1 } });
+                        }
+                        if far.0 < far.1 {
+                            pending.push(Pending { bound: bound.max(offset.abs()), visit: Visit::Range { start: far.0, end: far.1, depth: depth + 1 } });
+                        }
+                    },
+                }
+            }
+            found
+        });
+
+        found.into_iter()
+            .map(|(distance, index)| Neighbour { item: &self.items[index], index, distance })
+            .collect()
+    }
+}
+impl<T: IntoPosition> FromIterator<T> for NearestIndex<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+
+/// Part of the tree still to visit, no item in it is closer than `bound`
+#[derive(Clone,Copy,Debug)]
+enum Visit {
+    Item(usize),
+    Range { start: usize, end: usize, depth: usize },
+}
+
+/// Orders `BinaryHeap` so the smallest `bound` is popped first
+#[derive(Clone,Copy,Debug)]
+struct Pending {
+    bound: f64,
+    visit: Visit,
+}
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Pending { }
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Option::Some(self.cmp(other))
+    }
+}
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.bound.total_cmp(&self.bound)
+    }
+}
+
+/// Arrange `tree` so every range's middle element splits the rest on the
+/// axis `depth % 3`
+fn build(tree: &mut [usize], points: &[[f64; 3]], depth: usize) {
+    if tree.len() < 2 {
+        return;
+    }
+    let mid = tree.len() / 2;
+    let axis = depth % 3;
+    tree.select_nth_unstable_by(mid, |a, b| points[*a][axis].total_cmp(&points[*b][axis]));
+    let (below, above) = tree.split_at_mut(mid);
+    build(below, points, depth + 1);
+    build(&mut above[1..], points, depth + 1);
+}
+
+/// Earth centered earth fixed coordinates of a position, in meters
+fn to_cartesian(ellipsoid: &Ellipsoid, pos: &Position) -> [f64; 3] {
+    let a = ellipsoid.equatorial_radius();
+    let f = ellipsoid.flattening();
+    let e2 = f * (2.0 - f);
+    let (sin_lat, cos_lat) = pos.get_lat().to_radians().sin_cos();
+    let (sin_lon, cos_lon) = pos.get_lon().to_radians().sin_cos();
+    let n = a / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+    [n * cos_lat * cos_lon, n * cos_lat * sin_lon, n * (1.0 - e2) * sin_lat]
+}
+
+fn chord(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    let dx = a[0] - b[0];
+    let dy = a[1] - b[1];
+    let dz = a[2] - b[2];
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
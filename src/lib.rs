@@ -1,26 +1,98 @@
 
 use std::{
-    sync::Arc,
+    cmp::{Ordering},
     hash::{Hash,Hasher,BuildHasher},
-    time::{Duration},
-    mem::{swap},
 };
 
 #[macro_use] extern crate lazy_static;
 
-use tokio::sync::RwLock;
 use seahash::{SeaHasher};
-use moka::future::{Cache};
+use geographiclib_rs::{Geodesic};
+
+mod adaptive;
+pub use adaptive::{Adaptive};
+mod builder;
+#[cfg(any(feature = "async", feature = "sync"))]
+mod core;
+#[cfg(any(feature = "async", feature = "sync"))]
+mod key;
+#[cfg(any(feature = "async", feature = "sync"))]
+mod snapshot;
+#[cfg(any(feature = "async", feature = "sync"))]
+mod stats;
+#[cfg(any(feature = "async", feature = "sync"))]
+mod trace;
+#[cfg(any(feature = "async", feature = "sync"))]
+pub use stats::{CacheStats};
+pub use builder::{DistanceCacheBuilder};
+#[cfg(feature = "async")]
+pub mod future;
+#[cfg(feature = "async")]
+pub use future::{DistanceCache};
+#[cfg(feature = "sync")]
+pub mod sync;
+mod direct;
+pub use direct::{Destination,uncached_destination,uncached_destination_on};
+mod inverse;
+pub use inverse::{GeodesicData,uncached_geodesic,uncached_geodesic_on};
+#[cfg(feature = "geo")]
+mod geo;
+mod ellipsoid;
+pub use ellipsoid::{Ellipsoid};
+mod error;
+pub use error::{EllipsoidError,PositionError,SnapshotError};
+mod quantize;
+pub use quantize::{Quantization};
+mod batch;
+pub use batch::{DistanceMatrix,uncached_distance_matrix};
+mod path;
+pub use path::{PathLength,uncached_path_length};
+mod area;
+pub use area::{PolygonArea,Winding,uncached_polygon_area,uncached_polygon_area_on};
+mod knn;
+pub use knn::{NearestIndex,Neighbour};
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rayon")]
+pub use parallel::{par_uncached_distances,par_uncached_distances_iter};
 
 /// Location stores a Lat & Lon data.
 ///
 /// It provides a simple entry point for data entering the API and
 /// ensures data entering & exiting are in a uniform format.
-#[derive(Clone,Copy,Debug,PartialOrd)]
+///
+/// Equality, hashing, and ordering all work on the bit patterns of the
+/// coordinates (ordering uses `f64::total_cmp`, latitude first), so they
+/// agree with one another even for NaN and signed zeros. This makes
+/// `Position` usable as a `BTreeMap` key, it is also what decides which
+/// point of a pair is stored first in the caches.
+///
+/// With the `serde` feature positions (de)serialize as `{ lat, lon }`,
+/// deserializing goes through `Position::try_new` so invalid coordinates
+/// are rejected.
+#[derive(Clone,Copy,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "RawPosition"))]
 pub struct Position {
     lat: f64,
     lon: f64,
 }
+
+/// Unchecked form of `Position` that serde fills in before validation
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Position")]
+struct RawPosition {
+    lat: f64,
+    lon: f64,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<RawPosition> for Position {
+    type Error = PositionError;
+    fn try_from(raw: RawPosition) -> Result<Self,PositionError> {
+        Position::try_new(raw.lat, raw.lon)
+    }
+}
+
 impl PartialEq for Position {
     fn eq(&self, other: &Self) -> bool {
         (self.lat.to_ne_bytes() == other.lat.to_ne_bytes())
@@ -29,6 +101,17 @@ impl PartialEq for Position {
     }
 }
 impl Eq for Position { }
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.lat.total_cmp(&other.lat)
+            .then_with(|| self.lon.total_cmp(&other.lon))
+    }
+}
 impl Hash for Position {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write( self.lat.to_ne_bytes().as_ref());
@@ -36,19 +119,78 @@ impl Hash for Position {
     }
 }
 impl Position {
+    /// Build a position without checking it, see `Position::try_new`
     pub const fn new(lat: f64, lon: f64) -> Self {
         Self { lat, lon }
     }
+
+    /// Build a position, rejecting NaN, latitudes outside of [-90, 90]
+    /// and infinite longitudes.
+    pub fn try_new(lat: f64, lon: f64) -> Result<Self,PositionError> {
+        Self::new(lat, lon).validate()
+    }
+
+    /// Check the position is something the solver can work with
+    pub fn validate(self) -> Result<Self,PositionError> {
+        if self.lat.is_nan() || self.lon.is_nan() {
+            return Err(PositionError::NaN);
+        }
+        if !(-90.0..=90.0).contains(&self.lat) {
+            return Err(PositionError::LatitudeOutOfRange(self.lat));
+        }
+        if !self.lon.is_finite() {
+            return Err(PositionError::NonFiniteLongitude(self.lon));
+        }
+        Ok(self)
+    }
+
+    /// The canonical form of this position, used to key the caches.
+    ///
+    /// Longitude is wrapped onto (-180, 180], signed zeros are collapsed
+    /// to positive zero, and as longitude is meaningless at the poles it
+    /// is set to zero there. Positions describing the same place have
+    /// the same canonical form.
+    pub fn canonical(&self) -> Position {
+        self.canonical_with_offset().0
+    }
+
+    /// The canonical position along with how much azimuths measured at
+    /// this position change, in degrees, when moving from the canonical
+    /// position back to this one.
+    ///
+    /// At a pole azimuths are defined relative to the longitude
+    /// (approach along that meridian), away from the poles the offset
+    /// is always zero.
+    pub(crate) fn canonical_with_offset(&self) -> (Position,f64) {
+        fn positive_zero(x: f64) -> f64 {
+            if x == 0.0 { 0.0 } else { x }
+        }
+        let lat = positive_zero(self.lat);
+        let lon = positive_zero(normalize_azimuth(self.lon));
+        if lat == 90.0 {
+            (Position::new(lat, 0.0), lon)
+        } else if lat == -90.0 {
+            (Position::new(lat, 0.0), -lon)
+        } else {
+            (Position::new(lat, lon), 0.0)
+        }
+    }
+
+    /// Cheaper form of `validate` for deciding if a result may be cached
+    pub(crate) fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.lat) && self.lon.is_finite()
+    }
 }
 impl IntoPosition for Position {
     fn get_lat(&self) -> f64 { self.lat }
     fn get_lon(&self) -> f64 { self.lon }
     fn into_position(&self) -> Position {
-        self.clone()
+        *self
     }
 }
 
 /// Binding type for the API
+#[allow(clippy::wrong_self_convention)]
 pub trait IntoPosition {
     fn get_lat(&self) -> f64;
     fn get_lon(&self) -> f64;
@@ -66,26 +208,70 @@ pub trait IntoPosition {
 /// as part of the API as a convience. The only additional computation requriements
 /// are copying ~2 extra floating point values
 #[derive(Copy,Clone,PartialEq,PartialOrd,Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DistanceData {
-    /// Distance from `A` to `B` in meters on the WSG84 Spheroid
+    /// Distance from `A` to `B` in meters on the chosen `Ellipsoid`, WGS84 by default
     pub distance: f64,
     /// Bearing you'd have to have to reach `B` from `A`
     pub forward_azimuth: f64,
-    /// Bearing you'd have to face to reach `A` from `B`
+    /// Bearing you'd have on arrival at `B`, measured in the direction of travel.
+    ///
+    /// Add 180 degrees to get the bearing you'd have to face to reach `A` from `B`.
     pub backward_azimuth: f64,
 }
 impl DistanceData {
     /// Used because the cache stores southern most points first, to avoid caching (A->B & B->A)
     /// seperately.
-    fn swap_azimuth(&mut self, should_swap: bool) {
-        if should_swap {
+    ///
+    /// Both azimuths are measured in the direction of travel, so reversing the
+    /// geodesic swaps them and turns each one around.
+    #[cfg(any(feature = "async", feature = "sync"))]
+    fn reverse(&mut self, should_reverse: bool) {
+        use std::mem::{swap};
+
+        if should_reverse {
             swap(&mut self.forward_azimuth, &mut self.backward_azimuth);
+            self.forward_azimuth = normalize_azimuth(self.forward_azimuth + 180.0);
+            self.backward_azimuth = normalize_azimuth(self.backward_azimuth + 180.0);
         }
     }
 }
 
-#[derive(Default,Clone,Copy)]
-struct BuildSeaHasher {
+/// Maps an azimuth (or longitude) in degrees onto (-180,180]
+fn normalize_azimuth(azimuth: f64) -> f64 {
+    let azimuth = azimuth % 360.0;
+    if azimuth <= -180.0 {
+        azimuth + 360.0
+    } else if azimuth > 180.0 {
+        azimuth - 360.0
+    } else {
+        azimuth
+    }
+}
+
+/// `lon2 - lon1` reduced to [-180, 180], exactly as GeographicLib's
+/// `AngDiff` does, the sign decides which way an edge goes
+pub(crate) fn lon_diff(lon1: f64, lon2: f64) -> f64 {
+    let (diff, err) = two_sum(normalize_azimuth(-lon1), normalize_azimuth(lon2));
+    let diff = normalize_azimuth(diff);
+    if diff == 180.0 && err > 0.0 {
+        -180.0 + err
+    } else {
+        diff + err
+    }
+}
+
+/// `u + v` and the rounding error of that sum
+fn two_sum(u: f64, v: f64) -> (f64, f64) {
+    let sum = u + v;
+    let up = sum - v;
+    let vpp = sum - up;
+    (sum, -((up - u) + (vpp - v)))
+}
+
+/// Default hasher for cache keys.
+#[derive(Default,Clone,Copy,Debug)]
+pub struct BuildSeaHasher {
     #[allow(dead_code)] _data: u8,
 }
 impl BuildHasher for BuildSeaHasher {
@@ -94,85 +280,299 @@ impl BuildHasher for BuildSeaHasher {
         SeaHasher::new()
     }
 }
-unsafe impl Sync for BuildSeaHasher { }
-unsafe impl Send for BuildSeaHasher { }
 
+#[cfg(feature = "async")]
+lazy_static! {
+    static ref DISTANCE_CACHE: future::DistanceCache = future::DistanceCache::builder().name("global").build();
+}
+
+#[cfg(feature = "sync")]
 lazy_static! {
-    static ref DISTANCE_CACHE: Arc<RwLock<Cache<(Position,Position),DistanceData,BuildSeaHasher>>> = {
-        let cache = Cache::builder()
-            .time_to_idle(Duration::from_secs(90))
-            .initial_capacity(64)
-            .max_capacity(65356)
-            .build_with_hasher(BuildSeaHasher::default());
-        Arc::new(RwLock::new(cache))
-    };
+    static ref SYNC_DISTANCE_CACHE: sync::DistanceCache = sync::DistanceCache::builder().name("global_sync").build();
 }
 
-async fn time_future<F>(arg: F) -> (<F as std::future::Future>::Output,std::time::Duration)
+/// calculate the distance between 2 points on WGS84 without consulting any cache
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+pub fn uncached_distance<A,B>(a: &A, b: &B) -> DistanceData
 where
-    F: std::future::Future
+    A: IntoPosition,
+    B: IntoPosition,
 {
-    use tokio::time::Instant;
+    uncached_distance_on(&Ellipsoid::WGS84, a, b)
+}
 
-    let now = tokio::time::Instant::now();
-    let result = arg.await;
-    let later = now.elapsed();
-    (result,later)
+/// calculate the distance between 2 points on WGS84, rejecting invalid coordinates
+pub fn try_uncached_distance<A,B>(a: &A, b: &B) -> Result<DistanceData,PositionError>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    let a_pos = a.into_position().validate()?;
+    let b_pos = b.into_position().validate()?;
+    Ok(uncached_distance(&a_pos, &b_pos))
 }
 
-pub fn uncached_distance<A,B>(a: &A, b: &B) -> DistanceData
+/// calculate the distance between 2 points on the supplied ellipsoid
+///
+/// WGS84 uses a shared precomputed solver, other ellipsoids build a
+/// new solver on every call so prefer a `DistanceCache` for repeated queries.
+pub fn uncached_distance_on<A,B>(ellipsoid: &Ellipsoid, a: &A, b: &B) -> DistanceData
 where
     A: IntoPosition,
     B: IntoPosition,
 {
-    use geographiclib_rs::{Geodesic,InverseGeodesic};
+    ellipsoid.with_geodesic(|geod| solve_distance(geod, a.into_position(), b.into_position()))
+}
 
-    let a_pos = a.into_position();
-    let b_pos = b.into_position();
-    let flip = a_pos > b_pos;
-    let tup = if flip {
-        (b_pos, a_pos)
-    } else {
-        (a_pos, b_pos)
-    };
+/// Solve from `a_pos` to `b_pos` exactly as given, the caches order their
+/// keys themselves.
+fn solve_distance(geod: &Geodesic, a_pos: Position, b_pos: Position) -> DistanceData {
+    use geographiclib_rs::{InverseGeodesic};
 
-    let wgs84 = Geodesic::wgs84();
-    let (s12, azi_1, azi_2, _): (f64,f64,f64,f64) = wgs84.inverse(tup.0.get_lat(), tup.0.get_lon(), tup.1.get_lat(), tup.1.get_lon());
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+    let (s12, azi_1, azi_2, _): (f64,f64,f64,f64) = geod.inverse(a_pos.get_lat(), a_pos.get_lon(), b_pos.get_lat(), b_pos.get_lon());
+    #[cfg(feature = "tracing")]
+    tracing::trace!(solve_time = ?started.elapsed(), "solved inverse problem");
 
-    let mut dist = DistanceData {
+    DistanceData {
         distance: s12,
-        forward_azimuth: azi_1,
-        backward_azimuth: azi_2,
-    };
-    dist.swap_azimuth(flip);
-    dist
+        forward_azimuth: normalize_azimuth(azi_1),
+        backward_azimuth: normalize_azimuth(azi_2),
+    }
 }
 
 /// calculte the distance between 2 points
+///
+/// This uses a process wide `DistanceCache` with the default settings,
+/// construct your own `DistanceCache` if you need different sizing.
+#[cfg(feature = "async")]
 pub async fn distance<A,B>(a: &A, b: &B) -> DistanceData
 where
     A: IntoPosition,
     B: IntoPosition,
 {
-    let a_pos: Position = a.into_position();
-    let b_pos: Position = b.into_position();
-    let flip: bool = a_pos > b_pos;
-    let tup: (Position,Position) = if flip {
-        (b_pos, a_pos)
-    } else {
-        (a_pos, b_pos)
-    };
- 
-    match DISTANCE_CACHE.read().await.get(&tup).await {
-        Option::Some(mut dist) => {
-            dist.swap_azimuth(flip);
-            return dist;
-        },
-        Option::None => { }
-    };
-    let mut dist = uncached_distance(&tup.0, &tup.1);
-    DISTANCE_CACHE.write().await.insert(tup,dist.clone()).await;
-
-    dist.swap_azimuth(flip);
-    dist
+    DISTANCE_CACHE.distance(a, b).await
+}
+
+/// calculate the distance from every origin to every destination
+///
+/// Like `distance` this uses the process wide `DistanceCache`, only the
+/// pairs it does not hold are solved.
+#[cfg(feature = "async")]
+pub async fn distance_matrix<A,B>(origins: &[A], destinations: &[B]) -> DistanceMatrix
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    DISTANCE_CACHE.distance_matrix(origins, destinations).await
+}
+
+/// calculate the distance from `origin` to every destination
+///
+/// Like `distance` this uses the process wide `DistanceCache`.
+#[cfg(feature = "async")]
+pub async fn distances_from<A,B>(origin: &A, destinations: &[B]) -> Vec<DistanceData>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    DISTANCE_CACHE.distances_from(origin, destinations).await
+}
+
+/// calculate the length of a path, segments already in the cache are not
+/// solved again
+///
+/// Like `distance` this uses the process wide `DistanceCache`.
+#[cfg(feature = "async")]
+pub async fn path_length<A>(points: &[A]) -> PathLength
+where
+    A: IntoPosition,
+{
+    DISTANCE_CACHE.path_length(points).await
+}
+
+/// calculate the area and perimeter of a polygon, edges already in the
+/// cache are not solved again
+///
+/// Like `distance` this uses the process wide `DistanceCache`.
+#[cfg(feature = "async")]
+pub async fn polygon_area<A>(points: &[A]) -> PolygonArea
+where
+    A: IntoPosition,
+{
+    DISTANCE_CACHE.polygon_area(points).await
+}
+
+/// statistics for the process wide `DistanceCache` used by `distance` and friends
+#[cfg(feature = "async")]
+pub fn stats() -> CacheStats {
+    DISTANCE_CACHE.stats()
+}
+
+/// write the process wide `DistanceCache` to `path`, see `DistanceCache::save_snapshot`
+#[cfg(feature = "async")]
+pub fn save_snapshot<P: AsRef<std::path::Path>>(path: P) -> Result<u64,SnapshotError> {
+    DISTANCE_CACHE.save_snapshot(path)
+}
+
+/// warm start the process wide `DistanceCache` from `path`, see `DistanceCache::load_snapshot`
+#[cfg(feature = "async")]
+pub async fn load_snapshot<P: AsRef<std::path::Path>>(path: P) -> Result<u64,SnapshotError> {
+    DISTANCE_CACHE.load_snapshot(path).await
+}
+
+/// calculate the distance between 2 points, rejecting invalid coordinates
+///
+/// Like `distance` this uses the process wide `DistanceCache`.
+#[cfg(feature = "async")]
+pub async fn try_distance<A,B>(a: &A, b: &B) -> Result<DistanceData,PositionError>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    DISTANCE_CACHE.try_distance(a, b).await
+}
+
+/// find where you end up after travelling `distance` meters from `start`
+/// with an initial bearing of `azimuth` degrees
+///
+/// Like `distance` this uses the process wide `DistanceCache`.
+#[cfg(feature = "async")]
+pub async fn destination<A>(start: &A, azimuth: f64, distance: f64) -> Destination
+where
+    A: IntoPosition,
+{
+    DISTANCE_CACHE.destination(start, azimuth, distance).await
+}
+
+/// solve the inverse problem between 2 points, returning every output
+///
+/// Like `distance` this uses the process wide `DistanceCache`.
+#[cfg(feature = "async")]
+pub async fn geodesic<A,B>(a: &A, b: &B) -> GeodesicData
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    DISTANCE_CACHE.geodesic(a, b).await
+}
+
+/// calculate the distance between 2 points without an async runtime
+///
+/// This uses a process wide `sync::DistanceCache` with the default settings.
+#[cfg(feature = "sync")]
+pub fn distance_sync<A,B>(a: &A, b: &B) -> DistanceData
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.distance(a, b)
+}
+
+/// calculate the distance from every origin to every destination
+/// without an async runtime
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn distance_matrix_sync<A,B>(origins: &[A], destinations: &[B]) -> DistanceMatrix
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.distance_matrix(origins, destinations)
+}
+
+/// calculate the distance from `origin` to every destination without an
+/// async runtime
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn distances_from_sync<A,B>(origin: &A, destinations: &[B]) -> Vec<DistanceData>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.distances_from(origin, destinations)
+}
+
+/// calculate the length of a path without an async runtime, segments
+/// already in the cache are not solved again
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn path_length_sync<A>(points: &[A]) -> PathLength
+where
+    A: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.path_length(points)
+}
+
+/// calculate the area and perimeter of a polygon without an async runtime,
+/// edges already in the cache are not solved again
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn polygon_area_sync<A>(points: &[A]) -> PolygonArea
+where
+    A: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.polygon_area(points)
+}
+
+/// statistics for the process wide `sync::DistanceCache` used by
+/// `distance_sync` and friends
+#[cfg(feature = "sync")]
+pub fn stats_sync() -> CacheStats {
+    SYNC_DISTANCE_CACHE.stats()
+}
+
+/// write the process wide `sync::DistanceCache` to `path`, see `sync::DistanceCache::save_snapshot`
+#[cfg(feature = "sync")]
+pub fn save_snapshot_sync<P: AsRef<std::path::Path>>(path: P) -> Result<u64,SnapshotError> {
+    SYNC_DISTANCE_CACHE.save_snapshot(path)
+}
+
+/// warm start the process wide `sync::DistanceCache` from `path`, see `sync::DistanceCache::load_snapshot`
+#[cfg(feature = "sync")]
+pub fn load_snapshot_sync<P: AsRef<std::path::Path>>(path: P) -> Result<u64,SnapshotError> {
+    SYNC_DISTANCE_CACHE.load_snapshot(path)
+}
+
+/// calculate the distance between 2 points without an async runtime,
+/// rejecting invalid coordinates
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn try_distance_sync<A,B>(a: &A, b: &B) -> Result<DistanceData,PositionError>
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.try_distance(a, b)
+}
+
+/// find where you end up after travelling `distance` meters from `start`
+/// with an initial bearing of `azimuth` degrees, without an async runtime
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn destination_sync<A>(start: &A, azimuth: f64, distance: f64) -> Destination
+where
+    A: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.destination(start, azimuth, distance)
+}
+
+/// solve the inverse problem between 2 points returning every output,
+/// without an async runtime
+///
+/// Like `distance_sync` this uses the process wide `sync::DistanceCache`.
+#[cfg(feature = "sync")]
+pub fn geodesic_sync<A,B>(a: &A, b: &B) -> GeodesicData
+where
+    A: IntoPosition,
+    B: IntoPosition,
+{
+    SYNC_DISTANCE_CACHE.geodesic(a, b)
 }
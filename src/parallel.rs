@@ -0,0 +1,36 @@
+//! Data parallel solving for offline jobs, enabled by the `rayon` feature.
+//!
+//! These skip the caches entirely, for millions of mostly distinct pairs
+//! the lookup and insert overhead buys nothing.
+
+use rayon::prelude::*;
+
+use crate::{DistanceData,IntoPosition,uncached_distance};
+
+/// calculate the distance for every pair on all cores, without consulting any cache
+pub fn par_uncached_distances<A,B>(pairs: &[(A,B)]) -> Vec<DistanceData>
+where
+    A: IntoPosition + Sync,
+    B: IntoPosition + Sync,
+{
+    pairs.par_iter()
+        .map(|(a, b)| uncached_distance(a, b))
+        .collect()
+}
+
+/// calculate the distance for every pair produced by `pairs` on all cores,
+/// without consulting any cache
+///
+/// `pairs` can be any iterator, it is drained into a buffer first so the
+/// results are returned in the order of `pairs`.
+pub fn par_uncached_distances_iter<I,A,B>(pairs: I) -> Vec<DistanceData>
+where
+    I: IntoIterator<Item = (A,B)>,
+    A: IntoPosition + Send,
+    B: IntoPosition + Send,
+{
+    let pairs: Vec<(A,B)> = pairs.into_iter().collect();
+    pairs.into_par_iter()
+        .map(|(a, b)| uncached_distance(&a, &b))
+        .collect()
+}
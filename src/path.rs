@@ -0,0 +1,31 @@
+use crate::{DistanceData,IntoPosition,uncached_distance};
+
+/// Length of a path through a sequence of points.
+///
+/// `segments[i]` is the geodesic from point `i` to point `i + 1`, so a path
+/// of `n` points has `n - 1` segments, paths with fewer than 2 points have
+/// none and a length of zero.
+#[derive(Clone,Debug,PartialEq)]
+pub struct PathLength {
+    /// Sum of the segment distances in meters
+    pub length: f64,
+    /// Every segment in order
+    pub segments: Vec<DistanceData>,
+}
+impl PathLength {
+    pub(crate) fn new(segments: Vec<DistanceData>) -> Self {
+        let length = segments.iter().map(|seg| seg.distance).sum();
+        Self { length, segments }
+    }
+}
+
+/// calculate the length of a path on WGS84 without consulting any cache
+pub fn uncached_path_length<A>(points: &[A]) -> PathLength
+where
+    A: IntoPosition,
+{
+    let segments = points.windows(2)
+        .map(|seg| uncached_distance(&seg[0], &seg[1]))
+        .collect();
+    PathLength::new(segments)
+}
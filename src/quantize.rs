@@ -0,0 +1,97 @@
+use crate::{Ellipsoid,IntoPosition,Position,normalize_azimuth};
+
+/// How positions are snapped before they are used as cache keys.
+///
+/// Feeds that jitter in the last few decimal places almost never repeat a
+/// position exactly, so an exact key rarely hits. Snapping lets nearby
+/// queries share an entry, at the cost of returning the result for the
+/// snapped positions. `max_distance_error` bounds how far off the returned
+/// distance can be, azimuths are also approximate and degrade as the
+/// points get closer together.
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Quantization {
+    /// Positions are used exactly as given (the default)
+    #[default]
+    Exact,
+    /// Latitude & longitude are each rounded to the nearest multiple of
+    /// `step` degrees, a step that is not finite and positive snaps nothing
+    Grid { step: f64 },
+    /// Positions are moved to the centre of the geohash cell with
+    /// `precision` characters (1 through 12) that contains them
+    Geohash { precision: u8 },
+}
+impl Quantization {
+    /// Round to a grid of `micro_degrees` millionths of a degree,
+    /// `micro_degrees(1)` is ~0.11m at the equator
+    pub fn micro_degrees(micro_degrees: u32) -> Self {
+        Quantization::Grid { step: f64::from(micro_degrees) * 1e-6 }
+    }
+
+    /// Snap a position to the grid
+    pub fn quantize<A>(&self, pos: &A) -> Position
+    where
+        A: IntoPosition,
+    {
+        let pos = pos.into_position();
+        match *self {
+            Quantization::Exact => pos,
+            Quantization::Grid { step } if !is_step(step) => pos,
+            Quantization::Grid { step } => {
+                let lat = ((pos.get_lat() / step).round() * step).clamp(-90.0, 90.0);
+                let lon = (pos.get_lon() / step).round() * step;
+                Position::new(lat, lon)
+            },
+            Quantization::Geohash { .. } => {
+                let (lat_step, lon_step) = self.steps();
+                let lat = cell_centre(pos.get_lat(), -90.0, lat_step);
+                let lon = cell_centre(normalize_azimuth(pos.get_lon()), -180.0, lon_step);
+                Position::new(lat, lon)
+            },
+        }
+    }
+
+    /// Upper bound, in meters, on the difference between the distance
+    /// returned for a quantized pair and the exact distance.
+    ///
+    /// Each point moves at most half a step in latitude & longitude. No
+    /// degree of latitude is longer than `a / (1 - f)` radians and no
+    /// degree of longitude is longer than `a` radians, so each point moves
+    /// at most the sum of the two, and the distance can change by at
+    /// most what both points moved.
+    pub fn max_distance_error(&self, ellipsoid: &Ellipsoid) -> f64 {
+        let (lat_step, lon_step) = self.steps();
+        let a = ellipsoid.equatorial_radius();
+        let f = ellipsoid.flattening();
+        let per_point = (lat_step / 2.0).to_radians() * a / (1.0 - f)
+            + (lon_step / 2.0).to_radians() * a;
+        2.0 * per_point
+    }
+
+    /// Size of a cell in degrees of (latitude, longitude)
+    fn steps(&self) -> (f64,f64) {
+        match *self {
+            Quantization::Exact => (0.0, 0.0),
+            Quantization::Grid { step } if !is_step(step) => (0.0, 0.0),
+            Quantization::Grid { step } => (step, step),
+            Quantization::Geohash { precision } => {
+                // each character holds 5 bits, interleaved starting with longitude
+                let bits = 5 * i32::from(precision.clamp(1, 12));
+                let lon_bits = (bits + 1) / 2;
+                let lat_bits = bits / 2;
+                (180.0 / 2f64.powi(lat_bits), 360.0 / 2f64.powi(lon_bits))
+            },
+        }
+    }
+}
+
+/// `step` can be used as a grid size, `micro_degrees(0)` can not
+fn is_step(step: f64) -> bool {
+    step.is_finite() && step > 0.0
+}
+
+/// Centre of the cell of width `step` containing `x`, cells start at `min`
+fn cell_centre(x: f64, min: f64, step: f64) -> f64 {
+    let cells = ((-2.0 * min) / step).round();
+    let index = ((x - min) / step).floor().clamp(0.0, cells - 1.0);
+    min + (index + 0.5) * step
+}
@@ -0,0 +1,212 @@
+//! On disk format for `save_snapshot` / `load_snapshot`.
+//!
+//! Everything is little endian. The header is
+//!
+//! * magic `b"MKSN"`
+//! * format version, `u16`
+//! * crate version, `u8` length then that many bytes of UTF-8
+//! * ellipsoid, equatorial radius then flattening as `f64`
+//! * quantization, a `u8` tag (0 exact, 1 grid, 2 geohash) then the grid
+//!   step as `f64` or the geohash precision as `u8`
+//!
+//! followed by three sections, distances, geodesics and destinations, each
+//! a `u64` entry count then the entries as packed `f64`s. Keys are stored
+//! canonical & quantized, exactly as they are held in the cache.
+
+use std::{
+    fs::{self,File},
+    io::{BufWriter,Read,Write},
+    path::{Path,PathBuf},
+};
+
+use crate::{
+    Destination,DistanceData,Ellipsoid,GeodesicData,IntoPosition,Position,Quantization,SnapshotError,
+    key::{DestinationKey,PairKey,is_directional},
+};
+
+const MAGIC: &[u8; 4] = b"MKSN";
+const FORMAT: u16 = 1;
+const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Every entry of a cache, ready to be written or inserted.
+#[derive(Default)]
+pub(crate) struct Snapshot {
+    pub(crate) distances: Vec<(PairKey,DistanceData)>,
+    pub(crate) geodesics: Vec<(PairKey,GeodesicData)>,
+    pub(crate) destinations: Vec<(DestinationKey,Destination)>,
+}
+impl Snapshot {
+    /// Number of entries across all three sections
+    pub(crate) fn len(&self) -> u64 {
+        (self.distances.len() + self.geodesics.len() + self.destinations.len()) as u64
+    }
+
+    pub(crate) fn write_to<W: Write>(&self, w: &mut W, ellipsoid: Ellipsoid, quantization: &Quantization) -> Result<(),SnapshotError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&FORMAT.to_le_bytes())?;
+        w.write_all(&[CRATE_VERSION.len() as u8])?;
+        w.write_all(CRATE_VERSION.as_bytes())?;
+        put_f64s(w, &[ellipsoid.equatorial_radius(), ellipsoid.flattening()])?;
+        match *quantization {
+            Quantization::Exact => w.write_all(&[0])?,
+            Quantization::Grid { step } => {
+                w.write_all(&[1])?;
+                put_f64s(w, &[step])?;
+            },
+            Quantization::Geohash { precision } => w.write_all(&[2, precision])?,
+        };
+
+        w.write_all(&(self.distances.len() as u64).to_le_bytes())?;
+        for (key, data) in self.distances.iter() {
+            put_pair_key(w, key)?;
+            put_f64s(w, &[data.distance, data.forward_azimuth, data.backward_azimuth])?;
+        }
+        w.write_all(&(self.geodesics.len() as u64).to_le_bytes())?;
+        for (key, data) in self.geodesics.iter() {
+            put_pair_key(w, key)?;
+            put_f64s(w, &[
+                data.distance, data.forward_azimuth, data.backward_azimuth, data.arc_length,
+                data.reduced_length, data.geodesic_scale_ab, data.geodesic_scale_ba, data.area,
+            ])?;
+        }
+        w.write_all(&(self.destinations.len() as u64).to_le_bytes())?;
+        for (key, dest) in self.destinations.iter() {
+            put_f64s(w, &[
+                key.start.get_lat(), key.start.get_lon(), key.azimuth, key.distance,
+                dest.position.get_lat(), dest.position.get_lon(), dest.azimuth,
+            ])?;
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Read a snapshot, rejecting it unless it was written by this version
+    /// for the same ellipsoid & quantization
+    pub(crate) fn read_from<R: Read>(r: &mut R, ellipsoid: Ellipsoid, quantization: &Quantization) -> Result<Self,SnapshotError> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let mut format = [0u8; 2];
+        r.read_exact(&mut format)?;
+        let format = u16::from_le_bytes(format);
+        if format != FORMAT {
+            return Err(SnapshotError::UnsupportedFormat(format));
+        }
+        let mut version = vec![0u8; get_u8(r)? as usize];
+        r.read_exact(&mut version)?;
+        let version = String::from_utf8(version).map_err(|_| SnapshotError::Corrupt)?;
+        if version != CRATE_VERSION {
+            return Err(SnapshotError::CrateVersion(version));
+        }
+        let [radius, flattening] = get_f64s(r)?;
+        let found = Ellipsoid::new(radius, flattening);
+        if found != ellipsoid {
+            return Err(SnapshotError::EllipsoidMismatch(found));
+        }
+        let found = match get_u8(r)? {
+            0 => Quantization::Exact,
+            1 => {
+                let [step] = get_f64s(r)?;
+                Quantization::Grid { step }
+            },
+            2 => Quantization::Geohash { precision: get_u8(r)? },
+            _ => return Err(SnapshotError::Corrupt),
+        };
+        if found != *quantization {
+            return Err(SnapshotError::QuantizationMismatch(found));
+        }
+
+        let mut snapshot = Snapshot::default();
+        for _ in 0..get_u64(r)? {
+            let key = get_pair_key(r, ellipsoid)?;
+            let [distance, forward_azimuth, backward_azimuth] = get_f64s(r)?;
+            snapshot.distances.push((key, DistanceData { distance, forward_azimuth, backward_azimuth }));
+        }
+        for _ in 0..get_u64(r)? {
+            let key = get_pair_key(r, ellipsoid)?;
+            let [distance, forward_azimuth, backward_azimuth, arc_length, reduced_length, geodesic_scale_ab, geodesic_scale_ba, area] = get_f64s(r)?;
+            snapshot.geodesics.push((key, GeodesicData {
+                distance, forward_azimuth, backward_azimuth, arc_length,
+                reduced_length, geodesic_scale_ab, geodesic_scale_ba, area,
+            }));
+        }
+        for _ in 0..get_u64(r)? {
+            let [lat, lon, azimuth, distance, end_lat, end_lon, end_azimuth] = get_f64s(r)?;
+            let key = DestinationKey { ellipsoid, start: Position::new(lat, lon), azimuth, distance };
+            // the same keys `begin_destination` refuses to cache
+            if !(key.start.is_valid() && azimuth.is_finite() && distance.is_finite()) {
+                return Err(SnapshotError::Corrupt);
+            }
+            snapshot.destinations.push((key, Destination { position: Position::new(end_lat, end_lon), azimuth: end_azimuth }));
+        }
+        Ok(snapshot)
+    }
+}
+
+/// Write a snapshot to a temporary file beside `path` then rename it into
+/// place, so a save that is interrupted leaves the previous snapshot intact
+pub(crate) fn save_to<F>(path: &Path, write: F) -> Result<u64,SnapshotError>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<u64,SnapshotError>,
+{
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let saved = File::create(&temp).map_err(SnapshotError::from).and_then(|file| {
+        let mut file = BufWriter::new(file);
+        let written = write(&mut file)?;
+        file.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+        fs::rename(&temp, path)?;
+        Ok(written)
+    });
+    if saved.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    saved
+}
+
+fn put_f64s<W: Write>(w: &mut W, values: &[f64]) -> Result<(),SnapshotError> {
+    for value in values {
+        w.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn put_pair_key<W: Write>(w: &mut W, key: &PairKey) -> Result<(),SnapshotError> {
+    put_f64s(w, &[key.1.get_lat(), key.1.get_lon(), key.2.get_lat(), key.2.get_lon()])
+}
+
+fn get_u8<R: Read>(r: &mut R) -> Result<u8,SnapshotError> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn get_u64<R: Read>(r: &mut R) -> Result<u64,SnapshotError> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn get_f64s<R: Read, const N: usize>(r: &mut R) -> Result<[f64; N],SnapshotError> {
+    let mut out = [0.0; N];
+    let mut buf = [0u8; 8];
+    for value in out.iter_mut() {
+        r.read_exact(&mut buf)?;
+        *value = f64::from_le_bytes(buf);
+    }
+    Ok(out)
+}
+
+fn get_pair_key<R: Read>(r: &mut R, ellipsoid: Ellipsoid) -> Result<PairKey,SnapshotError> {
+    let [a_lat, a_lon, b_lat, b_lon] = get_f64s(r)?;
+    let (a_pos, b_pos) = (Position::new(a_lat, a_lon), Position::new(b_lat, b_lon));
+    // the same keys `Core::pair` refuses to cache
+    if !(a_pos.is_valid() && b_pos.is_valid()) || is_directional(a_pos, b_pos) {
+        return Err(SnapshotError::Corrupt);
+    }
+    Ok((ellipsoid, a_pos, b_pos))
+}
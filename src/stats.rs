@@ -0,0 +1,215 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64,Ordering},
+    },
+    time::{Duration,Instant},
+};
+
+/// Point in time view of how a `DistanceCache` is performing.
+///
+/// Counts are cumulative since the cache was built and cover the distance,
+/// geodesic and destination caches together. Compare `hit_rate` and
+/// `mean_miss_time` against the cost of a lookup to decide whether caching
+/// is paying for itself in a deployment.
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct CacheStats {
+    /// Lookups answered without running the solver
+    pub hits: u64,
+    /// Lookups that ran the solver
+    pub misses: u64,
+    /// Entries written to the cache
+    pub insertions: u64,
+    /// Entries removed because of the size limit, time-to-live, or time-to-idle
+    pub evictions: u64,
+    /// Entries currently held across the distance, geodesic and destination
+    /// tables, approximate as moka applies writes lazily. Each table is
+    /// bounded by `max_capacity` on its own, so this can be up to three
+    /// times that
+    pub entry_count: u64,
+    /// Total time spent in the solver on misses
+    pub miss_compute_time: Duration,
+    /// `distance` calls solved without consulting the cache, see `Adaptive`
+    pub bypassed: u64,
+}
+impl CacheStats {
+    /// Fraction of lookups that were hits, zero before the first lookup
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+
+    /// Average time the solver took per miss
+    pub fn mean_miss_time(&self) -> Duration {
+        if self.misses == 0 {
+            Duration::ZERO
+        } else {
+            self.miss_compute_time / (self.misses.min(u64::from(u32::MAX)) as u32)
+        }
+    }
+}
+
+/// The live counters behind `CacheStats`.
+///
+/// With the `metrics` feature every update is also forwarded to the
+/// recorder installed when the cache was built.
+pub(crate) struct Counters {
+    lookups: AtomicU64,
+    misses: AtomicU64,
+    insertions: AtomicU64,
+    bypassed: AtomicU64,
+    evictions: Evictions,
+    miss_nanos: AtomicU64,
+    #[cfg(feature = "metrics")]
+    instruments: Instruments,
+}
+impl Counters {
+    pub(crate) fn new(name: &str) -> Self {
+        #[cfg(feature = "metrics")]
+        let instruments = Instruments::new(name);
+        #[cfg(not(feature = "metrics"))]
+        let _ = name;
+        Self {
+            lookups: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            insertions: AtomicU64::new(0),
+            bypassed: AtomicU64::new(0),
+            evictions: Evictions {
+                count: Arc::new(AtomicU64::new(0)),
+                #[cfg(feature = "metrics")]
+                counter: instruments.evictions.clone(),
+            },
+            miss_nanos: AtomicU64::new(0),
+            #[cfg(feature = "metrics")]
+            instruments,
+        }
+    }
+
+    /// Shared with the eviction listeners of the underlying caches
+    pub(crate) fn evictions(&self) -> Evictions {
+        self.evictions.clone()
+    }
+
+    pub(crate) fn record_lookups(&self, lookups: u64) {
+        self.lookups.fetch_add(lookups, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        self.instruments.lookups.increment(lookups);
+    }
+
+    /// Record how long a single lookup took end to end, solving included
+    pub(crate) fn record_lookup_time(&self, took: Duration) {
+        #[cfg(feature = "metrics")]
+        self.instruments.lookup_seconds.record(took);
+        #[cfg(not(feature = "metrics"))]
+        let _ = took;
+    }
+
+    /// Record the solver running `misses` times, taking `took` in total
+    pub(crate) fn record_misses(&self, misses: u64, took: Duration) {
+        self.misses.fetch_add(misses, Ordering::Relaxed);
+        self.miss_nanos.fetch_add(took.as_nanos() as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        if misses > 0 {
+            self.instruments.misses.increment(misses);
+            let each = took / (misses.min(u64::from(u32::MAX)) as u32);
+            self.instruments.solve_seconds.record_many(each, misses as usize);
+        }
+    }
+
+    pub(crate) fn record_insertions(&self, insertions: u64) {
+        self.insertions.fetch_add(insertions, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        self.instruments.insertions.increment(insertions);
+    }
+
+    pub(crate) fn record_bypass(&self) {
+        self.bypassed.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        self.instruments.bypassed.increment(1);
+    }
+
+    pub(crate) fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn snapshot(&self, entry_count: u64) -> CacheStats {
+        let lookups = self.lookups.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        CacheStats {
+            hits: lookups.saturating_sub(misses),
+            misses,
+            insertions: self.insertions.load(Ordering::Relaxed),
+            evictions: self.evictions.count.load(Ordering::Relaxed),
+            entry_count,
+            miss_compute_time: Duration::from_nanos(self.miss_nanos.load(Ordering::Relaxed)),
+            bypassed: self.bypassed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts entries moka evicts, handed to each cache's eviction listener.
+#[derive(Clone)]
+pub(crate) struct Evictions {
+    count: Arc<AtomicU64>,
+    #[cfg(feature = "metrics")]
+    counter: metrics::Counter,
+}
+impl Evictions {
+    pub(crate) fn record(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        self.counter.increment(1);
+    }
+}
+
+/// Handles registered with the `metrics` recorder, labelled with the
+/// cache's name so several instances can be told apart.
+#[cfg(feature = "metrics")]
+struct Instruments {
+    lookups: metrics::Counter,
+    misses: metrics::Counter,
+    insertions: metrics::Counter,
+    evictions: metrics::Counter,
+    bypassed: metrics::Counter,
+    lookup_seconds: metrics::Histogram,
+    solve_seconds: metrics::Histogram,
+}
+#[cfg(feature = "metrics")]
+impl Instruments {
+    fn new(name: &str) -> Self {
+        use metrics::{Unit,counter,describe_counter,describe_histogram,histogram};
+
+        describe_counter!("memoized_kerney_lookups_total", "Cache lookups, hits and misses");
+        describe_counter!("memoized_kerney_misses_total", "Cache lookups that ran the solver");
+        describe_counter!("memoized_kerney_insertions_total", "Entries written to the cache");
+        describe_counter!("memoized_kerney_evictions_total", "Entries evicted by size, time-to-live, or time-to-idle");
+        describe_counter!("memoized_kerney_bypassed_total", "Calls that skipped the cache, see `Adaptive`");
+        describe_histogram!("memoized_kerney_lookup_seconds", Unit::Seconds, "Time taken by a cached call, solving included");
+        describe_histogram!("memoized_kerney_solve_seconds", Unit::Seconds, "Time the solver took per miss");
+
+        let name = name.to_string();
+        Self {
+            lookups: counter!("memoized_kerney_lookups_total", "cache" => name.clone()),
+            misses: counter!("memoized_kerney_misses_total", "cache" => name.clone()),
+            insertions: counter!("memoized_kerney_insertions_total", "cache" => name.clone()),
+            evictions: counter!("memoized_kerney_evictions_total", "cache" => name.clone()),
+            bypassed: counter!("memoized_kerney_bypassed_total", "cache" => name.clone()),
+            lookup_seconds: histogram!("memoized_kerney_lookup_seconds", "cache" => name.clone()),
+            solve_seconds: histogram!("memoized_kerney_solve_seconds", "cache" => name),
+        }
+    }
+}
+
+pub(crate) fn time_fn<F,R>(arg: F) -> (R,Duration)
+where
+    F: FnOnce() -> R,
+{
+    let now = Instant::now();
+    let result = arg();
+    let later = now.elapsed();
+    (result,later)
+}
@@ -0,0 +1,324 @@
+//! Memoization for blocking callers, built on `moka::sync::Cache`.
+//!
+//! This mirrors `future::DistanceCache` so callers such as rayon jobs do
+//! not need an async runtime to look up a cached value.
+
+use std::{
+    fs::{File},
+    hash::{Hash,BuildHasher},
+    io::{BufReader,Read,Write},
+    path::{Path},
+};
+
+use moka::sync::{Cache};
+
+use crate::{
+    BuildSeaHasher,CacheStats,Destination,DistanceCacheBuilder,DistanceData,DistanceMatrix,PathLength,PolygonArea,Ellipsoid,GeodesicData,IntoPosition,PositionError,Quantization,SnapshotError,
+    area::{Planimeter,edges},
+    core::{Batch,Core,from_pairs,matrix_pairs,path_pairs},
+    key::{DestinationKey,PairKey},
+    snapshot::{self},
+    stats::{Evictions},
+};
+
+/// Memoizes the results of `uncached_distance` and `uncached_destination`.
+///
+/// Each instance owns its own storage, so services (or tenants within
+/// a service) can size the cache for their own workload. The free
+/// functions `distance_sync`, `geodesic_sync`, and `destination_sync`
+/// are convience wrappers over a default instance.
+///
+/// Distances, full geodesics, and destinations are held in seperate
+/// caches, each of which is sized by the builder. Every result is
+/// computed on the `Ellipsoid` chosen by the builder, WGS84 by default.
+/// The solver for that ellipsoid is built once, up front, so misses only
+/// pay for solving the geodesic.
+///
+/// Keys are built from `Position::canonical`, so positions describing the
+/// same place (`-0.0` & `0.0`, longitude `370` & `10`, any longitude at a
+/// pole) share an entry. Positions that fail `Position::validate` are
+/// still solved, but the result is never cached. Neither are pairs whose
+/// latitudes mirror each other across the equator, GeographicLib picks
+/// between equally short geodesics by their direction so A->B can't be
+/// served from B->A. With a `Quantization` other than `Exact` results are
+/// computed for the snapped positions.
+///
+/// moka's caches are already concurrent, so lookups and inserts take no
+/// lock of their own and misses on different threads do not serialise.
+pub struct DistanceCache<S = BuildSeaHasher> {
+    core: Core,
+    cache: Cache<PairKey,DistanceData,S>,
+    geodesics: Cache<PairKey,GeodesicData,S>,
+    destinations: Cache<DestinationKey,Destination,S>,
+}
+impl DistanceCache<BuildSeaHasher> {
+    /// Start configuring a new cache
+    pub fn builder() -> DistanceCacheBuilder<Self> {
+        DistanceCacheBuilder::default()
+    }
+}
+impl<S> DistanceCache<S>
+where
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    /// calculate the distance between 2 points, consulting the cache first
+    pub fn distance<A,B>(&self, a: &A, b: &B) -> DistanceData
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        let lookup = match self.core.begin_distance(a, b) {
+            Result::Ok(lookup) => lookup,
+            Result::Err(dist) => return dist,
+        };
+        let dist = lookup.span.in_scope(|| self.cache.get_with(lookup.key, || {
+            self.core.solve_distance(&lookup)
+        }));
+        self.core.end_distance(lookup, dist)
+    }
+
+    /// calculate the distance from every origin to every destination,
+    /// only the pairs missing from the cache are solved
+    pub fn distance_matrix<A,B>(&self, origins: &[A], destinations: &[B]) -> DistanceMatrix
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        let data = self.distances(self.core.begin_batch(matrix_pairs(origins, destinations)));
+        DistanceMatrix::new(origins.len(), destinations.len(), data)
+    }
+
+    /// calculate the distance from `origin` to every destination, only the
+    /// pairs missing from the cache are solved
+    pub fn distances_from<A,B>(&self, origin: &A, destinations: &[B]) -> Vec<DistanceData>
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        self.distances(self.core.begin_batch(from_pairs(origin, destinations)))
+    }
+
+    /// calculate the length of the path through `points`, recurring
+    /// segments are served from the cache and only new ones are solved
+    pub fn path_length<A>(&self, points: &[A]) -> PathLength
+    where
+        A: IntoPosition,
+    {
+        PathLength::new(self.distances(self.core.begin_batch(path_pairs(points))))
+    }
+
+    /// Look up every distinct key of a batch, misses are single flight just
+    /// like `distance`.
+    ///
+    /// With the `rayon` feature the keys are looked up, and any misses
+    /// solved, in parallel.
+    fn distances(&self, batch: Batch) -> Vec<DistanceData> {
+        #[cfg(feature = "rayon")]
+        let values: Vec<DistanceData> = {
+            use rayon::prelude::*;
+            batch.keys().par_iter()
+                .map(|key| self.cache.get_with(*key, || self.core.solve_batch_key(key)))
+                .collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let values: Vec<DistanceData> = batch.keys().iter()
+            .map(|key| self.cache.get_with(*key, || self.core.solve_batch_key(key)))
+            .collect();
+        batch.finish(&values)
+    }
+
+    /// calculate the distance between 2 points, consulting the cache first
+    /// and rejecting invalid coordinates
+    pub fn try_distance<A,B>(&self, a: &A, b: &B) -> Result<DistanceData,PositionError>
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        let a_pos = a.into_position().validate()?;
+        let b_pos = b.into_position().validate()?;
+        Ok(self.distance(&a_pos, &b_pos))
+    }
+
+    /// solve the inverse problem between 2 points returning every output,
+    /// consulting the cache first
+    pub fn geodesic<A,B>(&self, a: &A, b: &B) -> GeodesicData
+    where
+        A: IntoPosition,
+        B: IntoPosition,
+    {
+        let lookup = match self.core.begin_geodesic(a, b) {
+            Result::Ok(lookup) => lookup,
+            Result::Err(data) => return data,
+        };
+        let data = self.geodesics.get_with(lookup.key, || {
+            self.core.solve_geodesic(&lookup.key)
+        });
+        self.core.end_geodesic(lookup, data)
+    }
+
+    /// calculate the area and perimeter of the polygon through `points` on
+    /// this cache's ellipsoid, edges are shared with `geodesic` so only new
+    /// ones are solved
+    pub fn polygon_area<A>(&self, points: &[A]) -> PolygonArea
+    where
+        A: IntoPosition,
+    {
+        let mut planimeter = Planimeter::new(self.core.c2());
+        for (a, b) in edges(points) {
+            planimeter.add_edge(a, b, &self.geodesic(&a, &b));
+        }
+        planimeter.finish()
+    }
+
+    /// find where you end up after travelling `distance` meters from `start`
+    /// with an initial bearing of `azimuth` degrees, consulting the cache first
+    pub fn destination<A>(&self, start: &A, azimuth: f64, distance: f64) -> Destination
+    where
+        A: IntoPosition,
+    {
+        let lookup = match self.core.begin_destination(start, azimuth, distance) {
+            Result::Ok(lookup) => lookup,
+            Result::Err(dest) => return dest,
+        };
+        let dest = self.destinations.get_with(lookup.key, || {
+            self.core.solve_destination(&lookup.key)
+        });
+        self.core.end_destination(lookup, dest)
+    }
+
+    /// Number of times this cache has run the solver.
+    ///
+    /// Concurrent misses on the same key are coalesced, only one caller
+    /// runs the solver while the others wait for its result.
+    pub fn solved(&self) -> u64 {
+        self.core.solved()
+    }
+
+    /// Hits, misses, evictions and solver time since the cache was built
+    pub fn stats(&self) -> CacheStats {
+        self.core.stats(self.entry_count())
+    }
+
+    /// The name given to the builder, `"default"` unless set
+    pub fn name(&self) -> &str {
+        self.core.name()
+    }
+
+    /// `distance` is currently skipping the cache, only ever true for caches
+    /// built with `DistanceCacheBuilder::adaptive`
+    pub fn bypassing(&self) -> bool {
+        self.core.bypassing()
+    }
+
+    /// How positions are snapped before being used as keys
+    pub fn quantization(&self) -> Quantization {
+        self.core.quantization()
+    }
+
+    /// The model every result is computed on
+    pub fn ellipsoid(&self) -> Ellipsoid {
+        self.core.ellipsoid()
+    }
+
+    /// Number of entries currently held across all three tables
+    /// (approximate, see `moka::sync::Cache::entry_count`), each table is
+    /// bounded by `DistanceCacheBuilder::max_capacity` seperately
+    pub fn entry_count(&self) -> u64 {
+        self.cache.entry_count()
+            + self.geodesics.entry_count()
+            + self.destinations.entry_count()
+    }
+
+    /// Discard every cached value
+    pub fn invalidate_all(&self) {
+        self.cache.invalidate_all();
+        self.geodesics.invalidate_all();
+        self.destinations.invalidate_all();
+    }
+
+    /// Write every cached entry to `path` so a later run can warm start
+    /// with `load_snapshot`, returns the number of entries written.
+    ///
+    /// The entries are written to `path` with `.tmp` appended then renamed
+    /// over `path`, so an interrupted save leaves the previous snapshot in
+    /// place.
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<u64,SnapshotError> {
+        snapshot::save_to(path.as_ref(), |file| self.write_snapshot(file))
+    }
+
+    /// Write every cached entry to `w`, see `save_snapshot`
+    pub fn write_snapshot<W: Write>(&self, w: &mut W) -> Result<u64,SnapshotError> {
+        self.core.write_snapshot(w, self.cache.iter(), self.geodesics.iter(), self.destinations.iter())
+    }
+
+    /// Insert every entry saved by `save_snapshot`, returns the number of
+    /// entries loaded.
+    ///
+    /// The snapshot must have been written by the same version of this
+    /// crate for a cache with the same ellipsoid & quantization, otherwise
+    /// nothing is loaded.
+    pub fn load_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<u64,SnapshotError> {
+        let mut file = BufReader::new(File::open(path)?);
+        self.read_snapshot(&mut file)
+    }
+
+    /// Insert every entry of a snapshot read from `r`, see `load_snapshot`
+    pub fn read_snapshot<R: Read>(&self, r: &mut R) -> Result<u64,SnapshotError> {
+        let snapshot = self.core.read_snapshot(r)?;
+        let loaded = snapshot.len();
+        for (key, value) in snapshot.distances {
+            self.cache.insert(key, value);
+        }
+        for (key, value) in snapshot.geodesics {
+            self.geodesics.insert(key, value);
+        }
+        for (key, value) in snapshot.destinations {
+            self.destinations.insert(key, value);
+        }
+        Ok(loaded)
+    }
+}
+
+impl DistanceCacheBuilder<DistanceCache<BuildSeaHasher>> {
+    /// Build a cache using `SeaHasher` for its keys
+    pub fn build(self) -> DistanceCache<BuildSeaHasher> {
+        self.build_with_hasher(BuildSeaHasher::default())
+    }
+
+    /// Build a cache using the supplied hasher for its keys
+    pub fn build_with_hasher<S>(self, hasher: S) -> DistanceCache<S>
+    where
+        S: BuildHasher + Clone + Send + Sync + 'static,
+    {
+        let core = Core::new(&self);
+        DistanceCache {
+            cache: build_cache(&self, hasher.clone(), core.evictions()),
+            geodesics: build_cache(&self, hasher.clone(), core.evictions()),
+            destinations: build_cache(&self, hasher, core.evictions()),
+            core,
+        }
+    }
+}
+
+fn build_cache<C,K,V,S>(config: &DistanceCacheBuilder<C>, hasher: S, evictions: Evictions) -> Cache<K,V,S>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    S: BuildHasher + Clone + Send + Sync + 'static,
+{
+    let mut builder = Cache::builder()
+        .initial_capacity(config.initial_capacity)
+        .max_capacity(config.max_capacity)
+        .eviction_listener(move |_, _, cause| {
+            if cause.was_evicted() {
+                evictions.record();
+            }
+        });
+    if let Option::Some(ttl) = config.time_to_live {
+        builder = builder.time_to_live(ttl);
+    }
+    if let Option::Some(tti) = config.time_to_idle {
+        builder = builder.time_to_idle(tti);
+    }
+    builder.build_with_hasher(hasher)
+}
@@ -0,0 +1,80 @@
+//! `tracing` spans for cache lookups.
+//!
+//! Without the `tracing` feature `LookupSpan` is zero sized and every
+//! method compiles away, so the caches do not need a `cfg` per call.
+
+use std::{
+    time::{Duration},
+};
+
+/// Covers one lookup, records whether it was a hit, whether the pair was
+/// flipped to build the key, and on a miss how long the solver took.
+pub(crate) struct LookupSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+impl LookupSpan {
+    pub(crate) fn new(operation: &'static str, cache: &str, flipped: bool) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::trace_span!(
+                "lookup",
+                operation,
+                cache,
+                flipped,
+                hit = true,
+                solve_time = tracing::field::Empty,
+            );
+            Self { span }
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (operation, cache, flipped);
+            Self { }
+        }
+    }
+
+    /// Record that the lookup missed and the solver took `took`
+    pub(crate) fn record_miss(&self, took: Duration) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("hit", false);
+            self.span.record("solve_time", tracing::field::debug(took));
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = took;
+    }
+
+    /// Run `func` inside the span
+    #[cfg(feature = "sync")]
+    pub(crate) fn in_scope<F,R>(&self, func: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.in_scope(func)
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            func()
+        }
+    }
+
+    /// Poll `fut` inside the span
+    #[cfg(feature = "async")]
+    pub(crate) async fn instrument<F>(&self, fut: F) -> F::Output
+    where
+        F: std::future::Future,
+    {
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::{Instrument};
+            fut.instrument(self.span.clone()).await
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            fut.await
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! An adaptive cache stops consulting moka once it measures caching as a
+//! net loss, and starts again after `probe_after` calls.
+
+use std::{
+    collections::hash_map::{DefaultHasher},
+    hash::{BuildHasher,Hasher},
+    time::{Duration},
+};
+
+use memoized_kerney::{sync,Adaptive,Position,uncached_distance};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+
+fn unique(i: u64) -> Position {
+    Position::new(10.0 + i as f64 * 1e-3, 20.0)
+}
+
+#[test]
+fn all_misses_trigger_bypass_then_reprobe() {
+    let cache = sync::DistanceCache::builder()
+        .adaptive(Adaptive { window: 16, probe_after: 8 })
+        .build();
+
+    // nothing ever hits, so any lookup overhead is a loss
+    for i in 0..16 {
+        cache.distance(&A, &unique(i));
+    }
+    assert!(cache.bypassing());
+
+    for i in 0..8 {
+        let b = unique(100 + i);
+        assert_eq!(cache.distance(&A, &b), uncached_distance(&A, &b));
+    }
+    let stats = cache.stats();
+    assert_eq!(stats.bypassed, 8);
+    assert_eq!(stats.misses, 16);
+    assert!(!cache.bypassing());
+
+    cache.distance(&A, &unique(200));
+    assert_eq!(cache.stats().misses, 17);
+}
+
+#[test]
+fn caches_without_adaptive_never_bypass() {
+    let cache = sync::DistanceCache::builder().build();
+    for i in 0..4096 {
+        cache.distance(&A, &unique(i));
+    }
+    assert!(!cache.bypassing());
+    assert_eq!(cache.stats().bypassed, 0);
+}
+
+#[tokio::test]
+async fn async_cache_bypasses_too() {
+    let cache = memoized_kerney::DistanceCache::builder()
+        .adaptive(Adaptive { window: 4, probe_after: 1024 })
+        .build();
+    for i in 0..4 {
+        cache.distance(&A, &unique(i)).await;
+    }
+    assert!(cache.bypassing());
+    cache.distance(&A, &unique(0)).await;
+    assert_eq!(cache.stats().bypassed, 1);
+}
+
+/// Makes every lookup far slower than solving, like heavy contention would
+#[derive(Clone,Default)]
+struct SlowHasher;
+impl BuildHasher for SlowHasher {
+    type Hasher = Slow;
+    fn build_hasher(&self) -> Slow {
+        Slow(DefaultHasher::new())
+    }
+}
+struct Slow(DefaultHasher);
+impl Hasher for Slow {
+    fn finish(&self) -> u64 {
+        std::thread::sleep(Duration::from_millis(1));
+        self.0.finish()
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+}
+
+#[test]
+fn all_hit_window_can_bypass() {
+    let cache = sync::DistanceCache::builder()
+        .adaptive(Adaptive { window: 8, probe_after: 1 })
+        .build_with_hasher(SlowHasher);
+    let b = unique(0);
+
+    // one miss gives a solve time to compare against
+    for _ in 0..8 {
+        cache.distance(&A, &b);
+    }
+    assert!(cache.bypassing());
+    cache.distance(&A, &b);
+    assert!(!cache.bypassing());
+
+    // a window of nothing but hits, each still slower than solving
+    for _ in 0..8 {
+        cache.distance(&A, &b);
+    }
+    assert_eq!(cache.stats().misses, 1);
+    assert!(cache.bypassing());
+}
@@ -0,0 +1,213 @@
+//! Polygon areas against GeographicLib's published Planimeter test values.
+
+use memoized_kerney::{sync,DistanceCache,Ellipsoid,IntoPosition,Position,PolygonArea,Winding,uncached_polygon_area,uncached_polygon_area_on};
+
+fn polygon(points: &[(f64,f64)]) -> Vec<Position> {
+    points.iter().map(|&(lat, lon)| Position::new(lat, lon)).collect()
+}
+
+/// (vertices, perimeter, area, perimeter tolerance, area tolerance), a
+/// NaN perimeter was not published for that case
+fn planimeter_cases() -> Vec<(Vec<Position>,f64,f64,f64,f64)> {
+    vec![
+        (polygon(&[(89.0, 0.0), (89.0, 90.0), (89.0, 180.0), (89.0, 270.0)]), 631819.8745, 24952305678.0, 1e-4, 1.0),
+        (polygon(&[(-89.0, 0.0), (-89.0, 90.0), (-89.0, 180.0), (-89.0, 270.0)]), 631819.8745, -24952305678.0, 1e-4, 1.0),
+        (polygon(&[(0.0, -1.0), (-1.0, 0.0), (0.0, 1.0), (1.0, 0.0)]), 627598.2731, 24619419146.0, 1e-4, 1.0),
+        (polygon(&[(90.0, 0.0), (0.0, 0.0), (0.0, 90.0)]), 30022685.0, 63758202715511.0, 1.0, 1.0),
+        (polygon(&[(89.0, 0.1), (89.0, 90.1), (89.0, -179.9)]), 539297.0, 12476152838.5, 1.0, 1.0),
+        (polygon(&[(9.0, -0.00000000000001), (9.0, 180.0), (9.0, 0.0)]), 36026861.0, 0.0, 1.0, 1.0),
+        (polygon(&[(9.0, 0.00000000000001), (9.0, 0.0), (9.0, 180.0)]), 36026861.0, 0.0, 1.0, 1.0),
+        (polygon(&[(66.562222222, 0.0), (66.562222222, 180.0), (66.562222222, 360.0)]), 10465729.0, 0.0, 1.0, 1.0),
+        (polygon(&[(89.0, -360.0), (89.0, -240.0), (89.0, -120.0), (89.0, 0.0), (89.0, 120.0), (89.0, 240.0)]), 1160741.0, 32415230256.0, 1.0, 1.0),
+        (polygon(&[(2.0, 1.0), (1.0, 2.0), (3.0, 3.0)]), f64::NAN, 18454562325.45119, 0.0, 1e-4),
+        (polygon(&[(1.0, 2.0), (2.0, 1.0), (3.0, 3.0)]), f64::NAN, -18454562325.45119, 0.0, 1e-4),
+    ]
+}
+
+fn check(name: &str, got: PolygonArea, perimeter: f64, area: f64, perimeter_tol: f64, area_tol: f64) {
+    if !perimeter.is_nan() {
+        assert!((got.perimeter - perimeter).abs() <= perimeter_tol, "{} perimeter {:?}", name, got);
+    }
+    assert!((got.area - area).abs() <= area_tol, "{} area {:?}", name, got);
+    if area < -area_tol {
+        assert_eq!(got.winding, Winding::Clockwise, "{}", name);
+    } else if area > area_tol {
+        assert_eq!(got.winding, Winding::CounterClockwise, "{}", name);
+    }
+}
+
+#[test]
+fn uncached_matches_planimeter() {
+    for (i, (points, perimeter, area, p_tol, a_tol)) in planimeter_cases().into_iter().enumerate() {
+        check(&format!("case {}", i), uncached_polygon_area(&points), perimeter, area, p_tol, a_tol);
+    }
+}
+
+#[test]
+fn cached_matches_planimeter() {
+    let cache = sync::DistanceCache::builder().build();
+    for _ in 0..2 {
+        for (i, (points, perimeter, area, p_tol, a_tol)) in planimeter_cases().into_iter().enumerate() {
+            check(&format!("case {}", i), cache.polygon_area(&points), perimeter, area, p_tol, a_tol);
+        }
+    }
+}
+
+#[tokio::test]
+async fn async_matches_planimeter() {
+    let cache = DistanceCache::builder().build();
+    for (i, (points, perimeter, area, p_tol, a_tol)) in planimeter_cases().into_iter().enumerate() {
+        check(&format!("case {}", i), cache.polygon_area(&points).await, perimeter, area, p_tol, a_tol);
+    }
+}
+
+#[test]
+fn matches_geographiclib_polygon_area() {
+    use geographiclib_rs::{Geodesic};
+
+    let geod = Geodesic::new(6378137.0, 1.0 / 298.257222101);
+    for (points, _, _, _, _) in planimeter_cases() {
+        let mut expected = geographiclib_rs::PolygonArea::new(&geod, geographiclib_rs::Winding::CounterClockwise);
+        for pos in points.iter() {
+            expected.add_point(pos.get_lat(), pos.get_lon());
+        }
+        let (perimeter, area, _) = expected.compute(true);
+        let got = uncached_polygon_area_on(&Ellipsoid::GRS80, &points);
+        assert!((got.perimeter - perimeter).abs() < 1e-6, "{:?} vs {}", got, perimeter);
+        assert!((got.area - area).abs() < 1e-2, "{:?} vs {}", got, area);
+    }
+}
+
+#[test]
+fn neighbouring_polygons_share_edges() {
+    let cache = sync::DistanceCache::builder().build();
+    let west = polygon(&[(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)]);
+    let east = polygon(&[(0.0, 1.0), (0.0, 2.0), (1.0, 2.0), (1.0, 1.0)]);
+    cache.polygon_area(&west);
+    assert_eq!(cache.solved(), 4);
+    // (0,1)->(1,1) is shared, walked in the opposite direction
+    cache.polygon_area(&east);
+    assert_eq!(cache.solved(), 7);
+    // the same ring listed the other way is served entirely from the cache
+    let mut reversed = west.clone();
+    reversed.reverse();
+    let forward = cache.polygon_area(&west);
+    let backward = cache.polygon_area(&reversed);
+    assert_eq!(cache.solved(), 7);
+    assert!((forward.area + backward.area).abs() < 1e-3);
+    assert_eq!(backward.winding, Winding::Clockwise);
+}
+
+#[test]
+fn degenerate_polygons() {
+    let empty: [Position; 0] = [];
+    assert_eq!(uncached_polygon_area(&empty), PolygonArea { area: 0.0, perimeter: 0.0, winding: Winding::CounterClockwise });
+    let point = polygon(&[(1.0, 1.0)]);
+    assert_eq!(uncached_polygon_area(&point).perimeter, 0.0);
+}
+
+/// Deterministic xorshift, uniform in [0, 1)
+fn rng(mut seed: u64) -> impl FnMut() -> f64 {
+    move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn geographiclib_area(points: &[Position]) -> (f64,f64) {
+    let geod = geographiclib_rs::Geodesic::wgs84();
+    let mut expected = geographiclib_rs::PolygonArea::new(&geod, geographiclib_rs::Winding::CounterClockwise);
+    for pos in points.iter() {
+        expected.add_point(pos.get_lat(), pos.get_lon());
+    }
+    let (perimeter, area, _) = expected.compute(true);
+    (perimeter, area)
+}
+
+fn check_geographiclib(points: &[Position], got: PolygonArea) {
+    let (perimeter, area) = geographiclib_area(points);
+    assert!((got.perimeter - perimeter).abs() < 1e-3, "{:?} {:?} vs {}", points, got, perimeter);
+    assert!((got.area - area).abs() < 1.0, "{:?} {:?} vs {}", points, got, area);
+}
+
+#[test]
+fn pole_to_pole_geodesics_match_uncached() {
+    use memoized_kerney::{uncached_geodesic};
+
+    let cache = sync::DistanceCache::builder().build();
+    // the longitudes are more than 180 apart, reduced they are the other way round
+    let pairs = [
+        ((90.0, -100.0), (90.0, 100.0)),
+        ((90.0, 170.0), (90.0, -170.0)),
+        ((-90.0, -170.0), (-90.0, 170.0)),
+        ((-90.0, 100.0), (-90.0, -100.0)),
+        ((90.0, -100.0), (-90.0, 100.0)),
+        ((-90.0, -100.0), (90.0, 100.0)),
+    ];
+    for &((a_lat, a_lon), (b_lat, b_lon)) in pairs.iter() {
+        let a = Position::new(a_lat, a_lon);
+        let b = Position::new(b_lat, b_lon);
+        // once as a miss, once as a hit, and reversed
+        for (a, b) in [(a, b), (a, b), (b, a)] {
+            let want = uncached_geodesic(&a, &b);
+            let got = cache.geodesic(&a, &b);
+            assert!((got.distance - want.distance).abs() < 1e-6, "{:?}->{:?} {:?} vs {:?}", a, b, got, want);
+            assert!((got.area - want.area).abs() < 1.0, "{:?}->{:?} {:?} vs {:?}", a, b, got, want);
+        }
+    }
+}
+
+#[test]
+fn consecutive_pole_vertices_match_geographiclib() {
+    let cache = sync::DistanceCache::builder().build();
+    let mut next = rng(0x2545f4914f6cdd1d);
+    for _ in 0..500 {
+        let pole = if next() < 0.5 { 90.0 } else { -90.0 };
+        let mut points = vec![
+            Position::new(pole, next() * 720.0 - 360.0),
+            Position::new(pole, next() * 720.0 - 360.0),
+        ];
+        for _ in 0..1 + (next() * 3.0) as usize {
+            points.push(Position::new(next() * 170.0 - 85.0, next() * 360.0 - 180.0));
+        }
+        check_geographiclib(&points, uncached_polygon_area(&points));
+        check_geographiclib(&points, cache.polygon_area(&points));
+    }
+}
+
+#[test]
+fn vertex_at_each_pole_matches_geographiclib() {
+    let cache = sync::DistanceCache::builder().build();
+    let mut next = rng(0x9e3779b97f4a7c15);
+    for _ in 0..500 {
+        let mut points = vec![
+            Position::new(90.0, next() * 360.0 - 180.0),
+            Position::new(-90.0, next() * 360.0 - 180.0),
+        ];
+        for _ in 0..1 + (next() * 3.0) as usize {
+            points.push(Position::new(next() * 170.0 - 85.0, next() * 360.0 - 180.0));
+        }
+        check_geographiclib(&points, uncached_polygon_area(&points));
+        check_geographiclib(&points, cache.polygon_area(&points));
+    }
+}
+
+#[test]
+fn mirrored_latitudes_match_geographiclib() {
+    let cache = sync::DistanceCache::builder().build();
+    let mut next = rng(0xd1b54a32d192ed03);
+    for _ in 0..500 {
+        // close to antipodal, where the shortest path can go either way
+        let lat = (next() * 170.0 - 85.0).round();
+        let lon = (next() * 360.0 - 180.0).round();
+        let points = vec![
+            Position::new(lat, lon),
+            Position::new(-lat, lon + 179.0 + next()),
+            Position::new(next() * 170.0 - 85.0, next() * 360.0 - 180.0),
+        ];
+        check_geographiclib(&points, uncached_polygon_area(&points));
+        check_geographiclib(&points, cache.polygon_area(&points));
+    }
+}
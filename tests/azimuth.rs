@@ -0,0 +1,90 @@
+//! Compares both the cached and uncached paths against direct calls into
+//! geographiclib, which never reorders its inputs.
+
+use geographiclib_rs::{Geodesic,InverseGeodesic};
+use proptest::prelude::*;
+use tokio::runtime::Runtime;
+
+use memoized_kerney::{distance,distance_sync,uncached_distance,DistanceData,Position};
+
+mod common;
+use common::{normalize};
+
+fn expected((a_lat,a_lon): (f64,f64), (b_lat,b_lon): (f64,f64)) -> DistanceData {
+    let (s12, azi1, azi2, _): (f64,f64,f64,f64) = Geodesic::wgs84().inverse(a_lat, a_lon, b_lat, b_lon);
+    DistanceData {
+        distance: s12,
+        forward_azimuth: normalize(azi1),
+        backward_azimuth: normalize(azi2),
+    }
+}
+
+/// Turning a reversed geodesic around costs a rounding or two
+fn assert_close(found: DistanceData, wanted: DistanceData) {
+    common::assert_close(found, wanted, 1e-12);
+}
+
+fn point_pair() -> impl Strategy<Value = ((f64,f64),(f64,f64))> {
+    ((-89.0f64..89.0, -180.0f64..180.0), (-89.0f64..89.0, -180.0f64..180.0))
+}
+
+/// Latitudes mirrored across the equator with longitudes close to opposite,
+/// where 2 geodesics of the same length exist and the direction decides
+fn mirrored_pair() -> impl Strategy<Value = ((f64,f64),(f64,f64))> {
+    (-89.0f64..89.0, -180.0f64..180.0, 170.0f64..=180.0, prop::bool::ANY)
+        .prop_map(|(lat, lon, apart, east)| ((lat, lon), (-lat, if east { lon + apart } else { lon - apart })))
+}
+
+fn check_uncached(a: (f64,f64), b: (f64,f64)) {
+    let a_pos = Position::new(a.0, a.1);
+    let b_pos = Position::new(b.0, b.1);
+    assert_eq!(uncached_distance(&a_pos, &b_pos), expected(a, b));
+    assert_eq!(uncached_distance(&b_pos, &a_pos), expected(b, a));
+}
+
+fn check_cached(a: (f64,f64), b: (f64,f64)) {
+    let rt = Runtime::new().unwrap();
+    let a_pos = Position::new(a.0, a.1);
+    let b_pos = Position::new(b.0, b.1);
+    rt.block_on(async {
+        // the second pair of lookups are served from the cache
+        for _ in 0..2 {
+            assert_close(distance(&a_pos, &b_pos).await, expected(a, b));
+            assert_close(distance(&b_pos, &a_pos).await, expected(b, a));
+        }
+    });
+    for _ in 0..2 {
+        assert_close(distance_sync(&a_pos, &b_pos), expected(a, b));
+        assert_close(distance_sync(&b_pos, &a_pos), expected(b, a));
+    }
+}
+
+proptest! {
+    #[test]
+    fn uncached_matches_geographiclib((a, b) in point_pair()) {
+        check_uncached(a, b);
+    }
+
+    #[test]
+    fn cached_matches_geographiclib((a, b) in point_pair()) {
+        check_cached(a, b);
+    }
+
+    #[test]
+    fn mirrored_uncached_matches_geographiclib((a, b) in mirrored_pair()) {
+        check_uncached(a, b);
+    }
+
+    #[test]
+    fn mirrored_cached_matches_geographiclib((a, b) in mirrored_pair()) {
+        check_cached(a, b);
+    }
+}
+
+#[test]
+fn either_of_two_geodesics() {
+    for (a, b) in [((30.0, 10.0), (-30.0, -170.0)), ((10.0, 0.0), (-10.0, 179.5)), ((90.0, 0.0), (-90.0, 0.0)), ((0.5, 0.0), (-0.5, 180.0))] {
+        check_uncached(a, b);
+        check_cached(a, b);
+    }
+}
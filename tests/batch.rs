@@ -0,0 +1,159 @@
+//! Batch lookups must agree with `uncached_distance` cell by cell, and keep
+//! the single flight guarantee of `distance`.
+
+use std::sync::{Arc,Barrier};
+
+use memoized_kerney::{sync,DistanceCache,DistanceData,DistanceMatrix,Position,uncached_distance,uncached_distance_matrix};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+const C: Position = Position::new(40.6413,-73.7781);
+const POLE: Position = Position::new(90.0, 45.0);
+const WORKERS: usize = 16;
+
+/// Azimuths of a zero length geodesic (the pole to itself) are arbitrary
+fn close(got: &DistanceData, want: &DistanceData) -> bool {
+    (got.distance - want.distance).abs() < 1e-6
+        && (want.distance == 0.0 || ((got.forward_azimuth - want.forward_azimuth).abs() < 1e-9
+        && (got.backward_azimuth - want.backward_azimuth).abs() < 1e-9))
+}
+
+fn check_matrix(matrix: &DistanceMatrix, origins: &[Position], destinations: &[Position]) {
+    assert_eq!(matrix.rows(), origins.len());
+    assert_eq!(matrix.cols(), destinations.len());
+    for (row, a) in origins.iter().enumerate() {
+        for (col, b) in destinations.iter().enumerate() {
+            let want = uncached_distance(a, b);
+            assert!(close(&matrix[(row, col)], &want), "{:?}->{:?} {:?} vs {:?}", a, b, matrix[(row, col)], want);
+        }
+    }
+}
+
+#[test]
+fn sync_matrix_matches_uncached() {
+    let cache = sync::DistanceCache::builder().build();
+    // B->A is A->B flipped, and the pole has a longitude the key drops
+    let origins = [A, B, C, POLE];
+    let destinations = [B, A, C, POLE, A];
+    cache.distance(&A, &C);
+    let matrix = cache.distance_matrix(&origins, &destinations);
+    check_matrix(&matrix, &origins, &destinations);
+    // and again, now served entirely from the cache
+    let solved = cache.solved();
+    check_matrix(&cache.distance_matrix(&origins, &destinations), &origins, &destinations);
+    assert_eq!(cache.solved(), solved);
+}
+
+#[tokio::test]
+async fn async_matrix_matches_uncached() {
+    let cache = DistanceCache::builder().build();
+    let origins = [A, B, C, POLE];
+    let destinations = [B, A, C, POLE, A];
+    let matrix = cache.distance_matrix(&origins, &destinations).await;
+    check_matrix(&matrix, &origins, &destinations);
+}
+
+#[test]
+fn distances_from_matches_uncached() {
+    let cache = sync::DistanceCache::builder().build();
+    let destinations = [B, C, POLE, Position::new(f64::NAN, 0.0), A];
+    let found = cache.distances_from(&A, &destinations);
+    assert_eq!(found.len(), destinations.len());
+    for (got, b) in found.iter().zip(destinations.iter()) {
+        let want = uncached_distance(&A, b);
+        if want.distance.is_nan() {
+            assert!(got.distance.is_nan());
+        } else {
+            assert!(close(got, &want), "A->{:?} {:?} vs {:?}", b, got, want);
+        }
+    }
+    // the NaN destination was solved but not cached
+    assert_eq!(cache.solved(), 4);
+}
+
+#[tokio::test]
+async fn async_distances_from_matches_uncached() {
+    let cache = DistanceCache::builder().build();
+    let destinations = [C, B, POLE];
+    let found = cache.distances_from(&B, &destinations).await;
+    for (got, b) in found.iter().zip(destinations.iter()) {
+        assert!(close(got, &uncached_distance(&B, b)));
+    }
+}
+
+#[test]
+fn matrix_shape_and_bounds() {
+    let matrix = uncached_distance_matrix(&[A, B], &[A, B, C]);
+    assert_eq!((matrix.rows(), matrix.cols()), (2, 3));
+    assert_eq!(matrix.as_slice().len(), 6);
+    assert_eq!(matrix.row(1).len(), 3);
+    assert_eq!(matrix.row(1)[2], *matrix.get(1, 2).unwrap());
+    assert_eq!(matrix.get(1, 2), Option::Some(&matrix[(1, 2)]));
+    assert_eq!(matrix.get(2, 0), Option::None);
+    assert_eq!(matrix.get(0, 3), Option::None);
+    assert_eq!(matrix.get(usize::MAX, usize::MAX), Option::None);
+
+    let empty = uncached_distance_matrix::<Position,Position>(&[A, B], &[]);
+    assert_eq!((empty.rows(), empty.cols()), (2, 0));
+    assert!(empty.row(1).is_empty());
+    assert_eq!(empty.get(0, 0), Option::None);
+}
+
+#[test]
+#[should_panic]
+fn matrix_index_out_of_bounds() {
+    let matrix = uncached_distance_matrix(&[A, B], &[A, B, C]);
+    let _ = matrix[(0, 3)];
+}
+
+/// Distinct points, enough that concurrent batches overlap while solving
+fn grid(count: usize) -> Vec<Position> {
+    (0..count).map(|i| Position::new(-60.0 + (i / 16) as f64, 20.0 + (i % 16) as f64)).collect()
+}
+
+#[test]
+fn sync_concurrent_batches_are_coalesced() {
+    let cache = Arc::new(sync::DistanceCache::builder().build());
+    let barrier = Arc::new(Barrier::new(WORKERS));
+    let points = grid(64);
+
+    let handles: Vec<_> = (0..WORKERS).map(|_| {
+        let cache = cache.clone();
+        let barrier = barrier.clone();
+        let points = points.clone();
+        std::thread::spawn(move || {
+            barrier.wait();
+            cache.distance_matrix(&points, &points)
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // 64 self pairs and 64 * 63 / 2 distinct unordered pairs, each solved once
+    assert_eq!(cache.solved(), 64 + 64 * 63 / 2);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn async_concurrent_batches_are_coalesced() {
+    let cache = Arc::new(DistanceCache::builder().build());
+    let barrier = Arc::new(tokio::sync::Barrier::new(WORKERS));
+    let points = grid(512);
+
+    let handles: Vec<_> = (0..WORKERS).map(|_| {
+        let cache = cache.clone();
+        let barrier = barrier.clone();
+        let points = points.clone();
+        tokio::spawn(async move {
+            barrier.wait().await;
+            cache.path_length(&points).await;
+            cache.distances_from(&points[0], &points).await
+        })
+    }).collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    // 511 path segments plus 511 new pairs from points[0] (0->1 is shared, 0->0 is new)
+    assert_eq!(cache.solved(), 511 + 511);
+}
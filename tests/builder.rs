@@ -0,0 +1,89 @@
+//! Every builder setting must reach the moka caches it configures.
+
+use std::{
+    collections::hash_map::{DefaultHasher},
+    hash::{BuildHasher},
+    sync::{Arc, atomic::{AtomicUsize,Ordering}},
+    thread::{sleep},
+    time::{Duration},
+};
+
+use memoized_kerney::{sync,DistanceCache,IntoPosition,Position};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+
+/// moka never returns an expired entry, so a second lookup after this long
+/// solves again if the entry expired
+const EXPIRY: Duration = Duration::from_millis(50);
+
+#[test]
+fn time_to_live_expires_entries() {
+    let cache = sync::DistanceCache::builder().time_to_live(EXPIRY).no_time_to_idle().build();
+    cache.distance(&A, &B);
+    sleep(EXPIRY * 2);
+    cache.distance(&A, &B);
+    assert_eq!(cache.solved(), 2);
+}
+
+#[tokio::test]
+async fn async_time_to_live_expires_entries() {
+    let cache = DistanceCache::builder().time_to_live(EXPIRY).no_time_to_idle().build();
+    cache.distance(&A, &B).await;
+    sleep(EXPIRY * 2);
+    cache.distance(&A, &B).await;
+    assert_eq!(cache.solved(), 2);
+}
+
+#[test]
+fn time_to_idle_expires_unread_entries() {
+    let cache = sync::DistanceCache::builder().time_to_idle(EXPIRY).build();
+    cache.distance(&A, &B);
+    sleep(EXPIRY * 2);
+    cache.distance(&A, &B);
+    assert_eq!(cache.solved(), 2);
+}
+
+#[test]
+fn no_time_to_idle_keeps_entries() {
+    let cache = sync::DistanceCache::builder().time_to_idle(EXPIRY).no_time_to_idle().build();
+    cache.distance(&A, &B);
+    sleep(EXPIRY * 2);
+    cache.distance(&A, &B);
+    assert_eq!(cache.solved(), 1);
+}
+
+#[test]
+fn max_capacity_evicts_entries() {
+    let cache = sync::DistanceCache::builder().max_capacity(10).build();
+    for step in 0..1000 {
+        cache.distance(&Position::new(A.get_lat() + step as f64 * 1e-4, A.get_lon()), &B);
+    }
+    let stats = cache.stats();
+    assert!(stats.evictions > 0, "{:?}", stats);
+    assert!(stats.entry_count < 1000, "{:?}", stats);
+}
+
+/// Counts the hashers the caches build
+#[derive(Clone,Default)]
+struct CountingHasher {
+    built: Arc<AtomicUsize>,
+}
+impl BuildHasher for CountingHasher {
+    type Hasher = DefaultHasher;
+    fn build_hasher(&self) -> DefaultHasher {
+        self.built.fetch_add(1, Ordering::Relaxed);
+        DefaultHasher::new()
+    }
+}
+
+#[test]
+fn build_with_hasher_hashes_keys() {
+    let hasher = CountingHasher::default();
+    let cache = sync::DistanceCache::builder().build_with_hasher(hasher.clone());
+    let built = hasher.built.load(Ordering::Relaxed);
+    let first = cache.distance(&A, &B);
+    assert!(hasher.built.load(Ordering::Relaxed) > built);
+    assert_eq!(cache.distance(&A, &B), first);
+    assert_eq!(cache.solved(), 1);
+}
@@ -0,0 +1,94 @@
+//! Equivalent positions must share a cache entry, and results served from
+//! that entry must still match the caller's own inputs.
+
+use memoized_kerney::{sync,uncached_destination,uncached_distance,uncached_geodesic,DistanceData,IntoPosition,Position};
+
+mod common;
+use common::{normalize};
+
+fn assert_close(found: DistanceData, wanted: DistanceData) {
+    common::assert_close(found, wanted, 1e-7);
+}
+
+/// Every pair in `equivalent` describes the same geodesic, after the first
+/// lookup the rest must be hits that still match the uncached answer.
+fn assert_shared(equivalent: &[(Position,Position)]) {
+    let cache = sync::DistanceCache::builder().build();
+    for (a, b) in equivalent {
+        assert_close(cache.distance(a, b), uncached_distance(a, b));
+        assert_close(cache.distance(b, a), uncached_distance(b, a));
+    }
+    assert_eq!(cache.solved(), 1, "{:?}", equivalent);
+}
+
+#[test]
+fn signed_zero() {
+    assert_shared(&[
+        (Position::new(0.0, 0.0), Position::new(10.0, 20.0)),
+        (Position::new(-0.0, 0.0), Position::new(10.0, 20.0)),
+        (Position::new(0.0, -0.0), Position::new(10.0, 20.0)),
+        (Position::new(-0.0, -0.0), Position::new(10.0, 20.0)),
+    ]);
+}
+
+#[test]
+fn antimeridian() {
+    assert_shared(&[
+        (Position::new(10.0, 180.0), Position::new(20.0, 170.0)),
+        (Position::new(10.0, -180.0), Position::new(20.0, 170.0)),
+        (Position::new(10.0, 540.0), Position::new(20.0, -190.0)),
+    ]);
+}
+
+#[test]
+fn wrapped_longitude() {
+    assert_shared(&[
+        (Position::new(10.0, 10.0), Position::new(20.0, 30.0)),
+        (Position::new(10.0, 370.0), Position::new(20.0, 30.0)),
+        (Position::new(10.0, -350.0), Position::new(20.0, 390.0)),
+    ]);
+}
+
+#[test]
+fn poles() {
+    for pole in [90.0, -90.0] {
+        assert_shared(&[
+            (Position::new(pole, 0.0), Position::new(10.0, 30.0)),
+            (Position::new(pole, 40.0), Position::new(10.0, 30.0)),
+            (Position::new(pole, -135.0), Position::new(10.0, 30.0)),
+            (Position::new(pole, 180.0), Position::new(10.0, 30.0)),
+        ]);
+    }
+}
+
+#[test]
+fn poles_full_geodesic() {
+    let cache = sync::DistanceCache::builder().build();
+    for pole in [90.0, -90.0] {
+        for lon in [0.0, 40.0, -135.0] {
+            let a = Position::new(pole, lon);
+            let b = Position::new(10.0, 30.0);
+            for (x, y) in [(a, b), (b, a)] {
+                let found = cache.geodesic(&x, &y);
+                let wanted = uncached_geodesic(&x, &y);
+                assert_close(found.into(), wanted.into());
+                assert!((found.area - wanted.area).abs() <= 1.0, "{:?} != {:?}", found, wanted);
+            }
+        }
+    }
+    assert_eq!(cache.solved(), 2);
+}
+
+#[test]
+fn destination_from_poles() {
+    let cache = sync::DistanceCache::builder().build();
+    for (lon, azimuth) in [(0.0, 60.0), (40.0, 100.0), (-20.0, 40.0), (0.0, 420.0)] {
+        let start = Position::new(90.0, lon);
+        let found = cache.destination(&start, azimuth, 1_000_000.0);
+        let wanted = uncached_destination(&start, azimuth, 1_000_000.0);
+        assert!((found.position.get_lat() - wanted.position.get_lat()).abs() <= 1e-9);
+        assert!(normalize(found.position.get_lon() - wanted.position.get_lon()).abs() <= 1e-9);
+        assert!(normalize(found.azimuth - wanted.azimuth).abs() <= 1e-9);
+    }
+    assert_eq!(cache.solved(), 1);
+}
@@ -0,0 +1,25 @@
+//! Azimuth helpers shared by the integration tests.
+
+use memoized_kerney::{DistanceData};
+
+/// Wrap an azimuth, or a difference of 2, onto (-180, 180]
+pub fn normalize(azimuth: f64) -> f64 {
+    let azimuth = azimuth % 360.0;
+    if azimuth <= -180.0 {
+        azimuth + 360.0
+    } else if azimuth > 180.0 {
+        azimuth - 360.0
+    } else {
+        azimuth
+    }
+}
+
+/// Distances must agree to a micrometer, azimuths must be normalised and
+/// agree to within `tolerance` degrees
+pub fn assert_close(found: DistanceData, wanted: DistanceData, tolerance: f64) {
+    assert!((found.distance - wanted.distance).abs() <= 1e-6, "{:?} != {:?}", found, wanted);
+    for (x, y) in [(found.forward_azimuth, wanted.forward_azimuth), (found.backward_azimuth, wanted.backward_azimuth)] {
+        assert!(x > -180.0 && x <= 180.0, "{} is not normalised", x);
+        assert!(normalize(x - y).abs() <= tolerance, "{:?} != {:?}", found, wanted);
+    }
+}
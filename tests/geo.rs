@@ -0,0 +1,36 @@
+//! `geo_types` points go straight into the API with x as longitude.
+
+use geo_types::{Coord,Point,coord,point};
+
+use memoized_kerney::{sync,IntoPosition,Position,uncached_distance};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(40.6413,-73.7781);
+
+#[test]
+fn x_is_longitude() {
+    let pt = point! { x: -121.9807130, y: 37.882704 };
+    assert_eq!(pt.into_position(), A);
+    let c = coord! { x: -121.9807130, y: 37.882704 };
+    assert_eq!(c.into_position(), A);
+    let small = Point::new(-121.5f32, 37.5f32);
+    assert_eq!(small.into_position(), Position::new(37.5, -121.5));
+}
+
+#[test]
+fn positions_convert_back() {
+    let pt: Point<f64> = A.into();
+    assert_eq!((pt.x(), pt.y()), (A.get_lon(), A.get_lat()));
+    let c: Coord<f64> = B.into();
+    assert_eq!(c.into_position(), B);
+}
+
+#[test]
+fn points_work_with_distance() {
+    let a: Point<f64> = A.into();
+    let b: Coord<f64> = B.into();
+    assert_eq!(uncached_distance(&a, &b), uncached_distance(&A, &B));
+    let cache = sync::DistanceCache::builder().build();
+    assert_eq!(cache.distance(&a, &b), cache.distance(&A, &B));
+    assert_eq!(cache.solved(), 1);
+}
@@ -0,0 +1,132 @@
+//! `NearestIndex` against a brute force search.
+
+use memoized_kerney::{Ellipsoid,IntoPosition,NearestIndex,Position,uncached_distance,uncached_distance_on};
+
+/// Deterministic scatter of points, dense enough that many have similar distances
+fn scatter(count: usize, seed: u64) -> Vec<Position> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+    (0..count)
+        .map(|_| Position::new(next() * 180.0 - 90.0, next() * 360.0 - 180.0))
+        .collect()
+}
+
+fn brute_force(points: &[Position], query: &Position, k: usize) -> Vec<usize> {
+    brute_force_on(&Ellipsoid::WGS84, points, query, k)
+}
+
+fn brute_force_on(ellipsoid: &Ellipsoid, points: &[Position], query: &Position, k: usize) -> Vec<usize> {
+    let mut all: Vec<(f64,usize)> = points.iter()
+        .enumerate()
+        .filter(|(_, pos)| pos.validate().is_ok())
+        .map(|(i, pos)| (uncached_distance_on(ellipsoid, query, pos).distance, i))
+        .collect();
+    all.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    all.into_iter().take(k).map(|(_, i)| i).collect()
+}
+
+#[test]
+fn matches_brute_force() {
+    let points = scatter(500, 7);
+    let index = NearestIndex::new(points.clone());
+    for query in scatter(50, 11).iter().chain([Position::new(90.0, 0.0), Position::new(0.0, 180.0)].iter()) {
+        for k in [1, 3, 10] {
+            let found = index.nearest(query, k);
+            let indices: Vec<usize> = found.iter().map(|n| n.index).collect();
+            assert_eq!(indices, brute_force(&points, query, k), "query {:?} k {}", query, k);
+            for n in found.iter() {
+                assert_eq!(n.distance, uncached_distance(query, n.item));
+            }
+        }
+    }
+}
+
+#[test]
+fn nearest_depots() {
+    // (name, lat, lon)
+    struct Depot(&'static str, f64, f64);
+    impl IntoPosition for Depot {
+        fn get_lat(&self) -> f64 { self.1 }
+        fn get_lon(&self) -> f64 { self.2 }
+    }
+    let index: NearestIndex<Depot> = vec![
+        Depot("london", 51.5074, -0.1278),
+        Depot("paris", 48.8566, 2.3522),
+        Depot("berlin", 52.5200, 13.4050),
+        Depot("madrid", 40.4168, -3.7038),
+        Depot("new york", 40.7128, -74.0060),
+    ].into_iter().collect();
+    let brussels = Position::new(50.8503, 4.3517);
+    let names: Vec<&str> = index.nearest(&brussels, 3).iter().map(|n| n.item.0).collect();
+    assert_eq!(names, vec!["paris", "london", "berlin"]);
+}
+
+#[test]
+fn small_and_empty_indexes() {
+    let empty: NearestIndex<Position> = NearestIndex::new(Vec::new());
+    assert!(empty.is_empty());
+    assert!(empty.nearest(&Position::new(0.0, 0.0), 5).is_empty());
+
+    let index = NearestIndex::new(scatter(4, 3));
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.nearest(&Position::new(0.0, 0.0), 10).len(), 4);
+    assert!(index.nearest(&Position::new(0.0, 0.0), 0).is_empty());
+}
+
+#[test]
+fn large_index_matches_brute_force() {
+    let points = scatter(20_000, 5);
+    let index = NearestIndex::new(points.clone());
+    for query in scatter(10, 13).iter() {
+        let found: Vec<usize> = index.nearest(query, 25).iter().map(|n| n.index).collect();
+        assert_eq!(found, brute_force(&points, query, 25), "query {:?}", query);
+    }
+}
+
+#[test]
+fn duplicates_are_ordered_by_index() {
+    let pos = Position::new(10.0, 20.0);
+    let index = NearestIndex::new(vec![Position::new(11.0, 20.0), pos, pos, pos, Position::new(10.0, 20.5)]);
+    let found: Vec<usize> = index.nearest(&pos, 4).iter().map(|n| n.index).collect();
+    assert_eq!(found, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn invalid_positions_are_skipped() {
+    let mut points = scatter(100, 17);
+    points[1] = Position::new(f64::NAN, 0.0);
+    points[2] = Position::new(91.0, 0.0);
+    points[3] = Position::new(0.0, f64::INFINITY);
+    let index = NearestIndex::new(points.clone());
+    // still listed, never returned
+    assert_eq!(index.len(), 100);
+    let query = points[0];
+    let found: Vec<usize> = index.nearest(&query, 100).iter().map(|n| n.index).collect();
+    assert_eq!(found.len(), 97);
+    assert_eq!(found, brute_force(&points, &query, 100));
+    assert!(found.iter().all(|i| *i > 3 || *i == 0));
+
+    let all_invalid = NearestIndex::new(vec![Position::new(f64::NAN, f64::NAN)]);
+    assert!(all_invalid.nearest(&query, 1).is_empty());
+    assert!(index.nearest(&Position::new(f64::NAN, 0.0), 3).is_empty());
+}
+
+#[test]
+fn other_ellipsoids() {
+    let points = scatter(500, 19);
+    for ellipsoid in [Ellipsoid::MARS, Ellipsoid::SPHERE, Ellipsoid::new(6378137.0, -1.0 / 50.0)] {
+        let index = NearestIndex::new_on(ellipsoid, points.clone());
+        assert_eq!(index.ellipsoid(), ellipsoid);
+        for query in scatter(20, 23).iter() {
+            let found = index.nearest(query, 5);
+            let indices: Vec<usize> = found.iter().map(|n| n.index).collect();
+            assert_eq!(indices, brute_force_on(&ellipsoid, &points, query, 5), "{:?} query {:?}", ellipsoid, query);
+            for n in found.iter() {
+                assert_eq!(n.distance, uncached_distance_on(&ellipsoid, query, n.item));
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! With the `metrics` feature each cache reports through the recorder
+//! installed when it was built, labelled with its name.
+
+use metrics_util::{
+    CompositeKey,
+    debugging::{DebugValue,DebuggingRecorder},
+};
+
+use memoized_kerney::{sync,Position};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+
+type Snapshot = Vec<(CompositeKey,Option<metrics::Unit>,Option<metrics::SharedString>,DebugValue)>;
+
+fn find<'a>(metrics: &'a Snapshot, name: &str, cache: &str) -> &'a DebugValue {
+    metrics.iter()
+        .find(|(key, _, _, _)| {
+            key.key().name() == name
+                && key.key().labels().any(|label| label.key() == "cache" && label.value() == cache)
+        })
+        .map(|(_, _, _, value)| value)
+        .unwrap_or_else(|| panic!("{} missing for {}", name, cache))
+}
+
+#[test]
+fn counters_and_histograms_are_labelled_by_name() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let (routing, billing) = metrics::with_local_recorder(&recorder, || {
+        (
+            sync::DistanceCache::builder().name("routing").build(),
+            sync::DistanceCache::builder().name("billing").build(),
+        )
+    });
+    assert_eq!(routing.name(), "routing");
+
+    routing.distance(&A, &B);
+    routing.distance(&B, &A);
+    routing.distance(&A, &B);
+    billing.distance(&A, &B);
+
+    let metrics: Snapshot = snapshotter.snapshot().into_vec();
+    assert_eq!(find(&metrics, "memoized_kerney_lookups_total", "routing"), &DebugValue::Counter(3));
+    assert_eq!(find(&metrics, "memoized_kerney_misses_total", "routing"), &DebugValue::Counter(1));
+    assert_eq!(find(&metrics, "memoized_kerney_insertions_total", "routing"), &DebugValue::Counter(1));
+    assert_eq!(find(&metrics, "memoized_kerney_lookups_total", "billing"), &DebugValue::Counter(1));
+    match find(&metrics, "memoized_kerney_lookup_seconds", "routing") {
+        DebugValue::Histogram(samples) => assert_eq!(samples.len(), 3),
+        other => panic!("unexpected {:?}", other),
+    }
+    match find(&metrics, "memoized_kerney_solve_seconds", "routing") {
+        DebugValue::Histogram(samples) => assert_eq!(samples.len(), 1),
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -0,0 +1,91 @@
+//! `Position`'s `Ord` must agree with its bitwise `Eq` & `Hash`, so it can
+//! key ordered and hashed collections alike.
+
+use std::{
+    cmp::{Ordering},
+    collections::{BTreeMap,HashSet},
+};
+
+use memoized_kerney::{Position};
+
+/// Coordinates that compare oddly as plain floats
+fn awkward() -> Vec<f64> {
+    vec![
+        0.0,
+        -0.0,
+        f64::NAN,
+        -f64::NAN,
+        f64::from_bits(f64::NAN.to_bits() | 1),
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        1.5,
+        -90.0,
+    ]
+}
+
+fn positions() -> Vec<Position> {
+    let mut positions = Vec::new();
+    for lat in awkward() {
+        for lon in awkward() {
+            positions.push(Position::new(lat, lon));
+        }
+    }
+    positions
+}
+
+#[test]
+fn ord_agrees_with_eq() {
+    let positions = positions();
+    for a in positions.iter() {
+        assert_eq!(a.cmp(a), Ordering::Equal);
+        for b in positions.iter() {
+            assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{:?} {:?}", a, b);
+            assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{:?} {:?}", a, b);
+            assert_eq!(a.partial_cmp(b), Option::Some(a.cmp(b)), "{:?} {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn signed_zeros_and_nans_are_distinct() {
+    assert_ne!(Position::new(0.0, 0.0), Position::new(-0.0, 0.0));
+    assert!(Position::new(-0.0, 0.0) < Position::new(0.0, 0.0));
+    assert!(Position::new(0.0, -0.0) < Position::new(0.0, 0.0));
+    // the same NaN bits are equal, different ones are not
+    assert_eq!(Position::new(f64::NAN, 0.0), Position::new(f64::NAN, 0.0));
+    assert_eq!(Position::new(f64::NAN, 0.0).cmp(&Position::new(f64::NAN, 0.0)), Ordering::Equal);
+    assert_ne!(Position::new(f64::NAN, 0.0), Position::new(-f64::NAN, 0.0));
+    assert_ne!(Position::new(f64::NAN, 0.0).cmp(&Position::new(-f64::NAN, 0.0)), Ordering::Equal);
+}
+
+#[test]
+fn latitude_orders_first() {
+    let mut sorted = vec![Position::new(1.0, -5.0), Position::new(-1.0, 5.0), Position::new(1.0, -6.0)];
+    sorted.sort();
+    assert_eq!(sorted, vec![Position::new(-1.0, 5.0), Position::new(1.0, -6.0), Position::new(1.0, -5.0)]);
+}
+
+#[test]
+fn btree_map_key() {
+    let positions = positions();
+    let mut map = BTreeMap::new();
+    for (i, pos) in positions.iter().enumerate() {
+        assert_eq!(map.insert(*pos, i), Option::None, "{:?} already present", pos);
+    }
+    // the same keys as a hashed set, nothing collapsed or duplicated
+    let set: HashSet<Position> = positions.iter().copied().collect();
+    assert_eq!(map.len(), positions.len());
+    assert_eq!(set.len(), positions.len());
+    for (i, pos) in positions.iter().enumerate() {
+        assert_eq!(map.get(pos), Option::Some(&i), "{:?}", pos);
+        assert!(set.contains(pos));
+    }
+    assert!(map.keys().zip(map.keys().skip(1)).all(|(a, b)| a < b));
+
+    let mut visits: BTreeMap<Position,u32> = BTreeMap::new();
+    for pos in [Position::new(51.5074, -0.1278), Position::new(48.8566, 2.3522), Position::new(51.5074, -0.1278)] {
+        *visits.entry(pos).or_insert(0) += 1;
+    }
+    assert_eq!(visits.get(&Position::new(51.5074, -0.1278)), Option::Some(&2));
+    assert_eq!(visits.keys().next(), Option::Some(&Position::new(48.8566, 2.3522)));
+}
@@ -0,0 +1,38 @@
+//! Parallel solving must return what the sequential loop does, in order.
+
+use memoized_kerney::{Position,par_uncached_distances,par_uncached_distances_iter,uncached_distance};
+
+fn pairs() -> Vec<(Position,Position)> {
+    (0..1000).map(|i| {
+        let step = i as f64;
+        (Position::new(-80.0 + step * 0.16, step * 0.3), Position::new(40.6413, -73.7781 + step * 0.1))
+    }).collect()
+}
+
+#[test]
+fn slices_match_sequential() {
+    let pairs = pairs();
+    let found = par_uncached_distances(&pairs);
+    assert_eq!(found.len(), pairs.len());
+    for (got, (a, b)) in found.iter().zip(pairs.iter()) {
+        assert_eq!(*got, uncached_distance(a, b));
+    }
+}
+
+#[test]
+fn iterators_match_sequential() {
+    let pairs = pairs();
+    // a lazy sequential iterator, not a slice
+    let found = par_uncached_distances_iter((0..pairs.len()).map(|i| pairs[i]));
+    assert_eq!(found, par_uncached_distances(&pairs));
+
+    let found = par_uncached_distances_iter(pairs.clone());
+    assert_eq!(found, par_uncached_distances(&pairs));
+}
+
+#[test]
+fn empty_input() {
+    let none: [(Position,Position); 0] = [];
+    assert!(par_uncached_distances(&none).is_empty());
+    assert!(par_uncached_distances_iter(none).is_empty());
+}
@@ -0,0 +1,61 @@
+//! Path lengths sum their segments and reuse cached ones.
+
+use memoized_kerney::{sync,DistanceCache,Position,uncached_distance,uncached_path_length};
+
+fn track(start: usize, len: usize) -> Vec<Position> {
+    (start..start + len)
+        .map(|i| Position::new(37.88 + i as f64 * 1e-3, -121.98 + (i % 3) as f64 * 1e-3))
+        .collect()
+}
+
+#[test]
+fn segments_match_uncached() {
+    let points = track(0, 6);
+    let path = uncached_path_length(&points);
+    assert_eq!(path.segments.len(), 5);
+    for (i, seg) in path.segments.iter().enumerate() {
+        assert_eq!(*seg, uncached_distance(&points[i], &points[i + 1]));
+    }
+    let total: f64 = path.segments.iter().map(|seg| seg.distance).sum();
+    assert_eq!(path.length, total);
+
+    let cache = sync::DistanceCache::builder().build();
+    assert_eq!(cache.path_length(&points), path);
+}
+
+#[test]
+fn short_paths_are_empty() {
+    let cache = sync::DistanceCache::builder().build();
+    for points in [track(0, 0), track(0, 1)] {
+        let path = cache.path_length(&points);
+        assert_eq!(path.length, 0.0);
+        assert!(path.segments.is_empty());
+    }
+    assert_eq!(cache.solved(), 0);
+}
+
+#[test]
+fn overlapping_tracks_reuse_segments() {
+    let cache = sync::DistanceCache::builder().build();
+    cache.path_length(&track(0, 10));
+    assert_eq!(cache.solved(), 9);
+    // shares 5 of its 9 segments with the first track
+    cache.path_length(&track(4, 10));
+    assert_eq!(cache.solved(), 13);
+
+    // walking a track backwards reuses every segment
+    let mut reversed = track(0, 10);
+    reversed.reverse();
+    let back = cache.path_length(&reversed);
+    assert_eq!(cache.solved(), 13);
+    assert!((back.length - uncached_path_length(&track(0, 10)).length).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn async_path_length() {
+    let cache = DistanceCache::builder().build();
+    let points = track(0, 4);
+    assert_eq!(cache.path_length(&points).await, uncached_path_length(&points));
+    cache.path_length(&points).await;
+    assert_eq!(cache.solved(), 3);
+}
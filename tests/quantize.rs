@@ -0,0 +1,55 @@
+//! Quantized lookups must stay within the documented distance error bound.
+
+use proptest::prelude::*;
+
+use memoized_kerney::{sync,uncached_distance,Ellipsoid,Position,Quantization};
+
+fn check(quantization: Quantization, a: (f64,f64), b: (f64,f64)) {
+    let cache = sync::DistanceCache::builder().quantization(quantization).build();
+    let a = Position::new(a.0, a.1);
+    let b = Position::new(b.0, b.1);
+    let found = cache.distance(&a, &b).distance;
+    let exact = uncached_distance(&a, &b).distance;
+    let bound = quantization.max_distance_error(&Ellipsoid::WGS84);
+    assert!((found - exact).abs() <= bound, "{} vs {} exceeds {}", found, exact, bound);
+}
+
+proptest! {
+    #[test]
+    fn grid_within_bound(a in (-90.0f64..90.0, -180.0f64..180.0), b in (-90.0f64..90.0, -180.0f64..180.0)) {
+        check(Quantization::micro_degrees(50), a, b);
+    }
+
+    #[test]
+    fn geohash_within_bound(a in (-90.0f64..90.0, -180.0f64..180.0), b in (-90.0f64..90.0, -180.0f64..180.0)) {
+        check(Quantization::Geohash { precision: 7 }, a, b);
+    }
+}
+
+#[test]
+fn jitter_shares_an_entry() {
+    let cache = sync::DistanceCache::builder().quantization(Quantization::micro_degrees(10)).build();
+    let b = Position::new(37.883463, -121.980988);
+    for jitter in [0.0, 1e-7, -2e-7, 3e-7] {
+        let a = Position::new(37.882704 + jitter, -121.9807130 - jitter);
+        cache.distance(&a, &b);
+    }
+    assert_eq!(cache.solved(), 1);
+}
+
+#[test]
+fn unusable_steps_snap_nothing() {
+    let a = Position::new(37.882704, -121.9807130);
+    let b = Position::new(40.6413, -73.7781);
+    let exact = uncached_distance(&a, &b).distance;
+    for quantization in [Quantization::micro_degrees(0), Quantization::Grid { step: f64::NAN }, Quantization::Grid { step: -1e-5 }, Quantization::Grid { step: f64::INFINITY }] {
+        assert_eq!(quantization.quantize(&a), a);
+        assert_eq!(quantization.max_distance_error(&Ellipsoid::WGS84), 0.0);
+
+        let cache = sync::DistanceCache::builder().quantization(quantization).build();
+        assert_eq!(cache.distance(&a, &b).distance, exact, "{:?}", quantization);
+        assert_eq!(cache.distance(&a, &b).distance, exact, "{:?}", quantization);
+        assert_eq!(cache.solved(), 1);
+        assert!(cache.destination(&a, 45.0, 1000.0).position.validate().is_ok());
+    }
+}
@@ -0,0 +1,54 @@
+//! With the `serde` feature positions and results round trip through JSON
+//! and bincode, and invalid positions are refused on the way in.
+
+use memoized_kerney::{Destination,DistanceData,GeodesicData,Position,uncached_destination,uncached_distance,uncached_geodesic};
+
+use proptest::prelude::*;
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(40.6413,-73.7781);
+
+#[test]
+fn position_json_shape() {
+    assert_eq!(serde_json::to_string(&A).unwrap(), r#"{"lat":37.882704,"lon":-121.980713}"#);
+    let pos: Position = serde_json::from_str(r#"{"lon":-121.980713,"lat":37.882704}"#).unwrap();
+    assert_eq!(pos, A);
+}
+
+#[test]
+fn invalid_positions_are_rejected() {
+    let err = serde_json::from_str::<Position>(r#"{"lat":91.0,"lon":0.0}"#).unwrap_err();
+    assert!(err.to_string().contains("latitude 91 is outside of [-90, 90]"), "{}", err);
+    assert!(serde_json::from_str::<Position>(r#"{"lat":0.0}"#).is_err());
+
+    let nan = bincode::serialize(&Position::new(f64::NAN, 0.0)).unwrap();
+    assert!(bincode::deserialize::<Position>(&nan).is_err());
+    let infinite = bincode::serialize(&Position::new(0.0, f64::INFINITY)).unwrap();
+    assert!(bincode::deserialize::<Position>(&infinite).is_err());
+}
+
+#[test]
+fn results_round_trip() {
+    let dist = uncached_distance(&A, &B);
+    assert_eq!(serde_json::from_str::<DistanceData>(&serde_json::to_string(&dist).unwrap()).unwrap(), dist);
+    assert_eq!(bincode::deserialize::<DistanceData>(&bincode::serialize(&dist).unwrap()).unwrap(), dist);
+
+    let geod = uncached_geodesic(&A, &B);
+    assert_eq!(serde_json::from_str::<GeodesicData>(&serde_json::to_string(&geod).unwrap()).unwrap(), geod);
+    assert_eq!(bincode::deserialize::<GeodesicData>(&bincode::serialize(&geod).unwrap()).unwrap(), geod);
+
+    let dest = uncached_destination(&A, 73.0, 500.0);
+    assert_eq!(serde_json::from_str::<Destination>(&serde_json::to_string(&dest).unwrap()).unwrap(), dest);
+    assert_eq!(bincode::deserialize::<Destination>(&bincode::serialize(&dest).unwrap()).unwrap(), dest);
+}
+
+proptest! {
+    #[test]
+    fn positions_round_trip(lat in -90.0f64..=90.0, lon in -540.0f64..540.0) {
+        let pos = Position::new(lat, lon);
+        let json: Position = serde_json::from_str(&serde_json::to_string(&pos).unwrap()).unwrap();
+        prop_assert_eq!(json, pos);
+        let binary: Position = bincode::deserialize(&bincode::serialize(&pos).unwrap()).unwrap();
+        prop_assert_eq!(binary, pos);
+    }
+}
@@ -0,0 +1,79 @@
+//! Concurrent misses on the same key must only run the solver once.
+
+use std::sync::{Arc,Barrier};
+
+use memoized_kerney::{sync,DistanceCache,IntoPosition,Position};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+const WORKERS: usize = 32;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn async_misses_are_coalesced() {
+    let cache = Arc::new(DistanceCache::builder().build());
+    let barrier = Arc::new(tokio::sync::Barrier::new(WORKERS));
+
+    let handles: Vec<_> = (0..WORKERS).map(|worker| {
+        let cache = cache.clone();
+        let barrier = barrier.clone();
+        tokio::spawn(async move {
+            barrier.wait().await;
+            // half the workers ask for B->A, which shares the A->B entry
+            if worker % 2 == 0 {
+                cache.distance(&A, &B).await
+            } else {
+                cache.distance(&B, &A).await
+            }
+        })
+    }).collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    assert_eq!(cache.solved(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn async_distinct_keys_are_each_solved_once() {
+    let cache = Arc::new(DistanceCache::builder().build());
+
+    let handles: Vec<_> = (0..WORKERS).map(|_| {
+        let cache = cache.clone();
+        tokio::spawn(async move {
+            for step in 0..16 {
+                let a = Position::new(A.get_lat() + (step as f64) * 1e-3, A.get_lon());
+                cache.distance(&a, &B).await;
+                cache.destination(&a, 73.0, 500.0).await;
+            }
+        })
+    }).collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    assert_eq!(cache.solved(), 32);
+}
+
+#[test]
+fn sync_misses_are_coalesced() {
+    let cache = Arc::new(sync::DistanceCache::builder().build());
+    let barrier = Arc::new(Barrier::new(WORKERS));
+
+    let handles: Vec<_> = (0..WORKERS).map(|worker| {
+        let cache = cache.clone();
+        let barrier = barrier.clone();
+        std::thread::spawn(move || {
+            barrier.wait();
+            if worker % 2 == 0 {
+                cache.geodesic(&A, &B)
+            } else {
+                cache.geodesic(&B, &A)
+            }
+        })
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(cache.solved(), 1);
+}
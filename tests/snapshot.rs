@@ -0,0 +1,133 @@
+//! Snapshots written by one cache warm start another.
+
+use std::{
+    path::{PathBuf},
+};
+
+use memoized_kerney::{sync,DistanceCache,Ellipsoid,IntoPosition,Position,Quantization,SnapshotError};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+const C: Position = Position::new(40.6413,-73.7781);
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("memoized_kerney_{}_{}.snapshot", name, std::process::id()))
+}
+
+#[test]
+fn sync_round_trip() {
+    let path = temp_path("sync_round_trip");
+    let first = sync::DistanceCache::builder().build();
+    let distance = first.distance(&A, &B);
+    let geodesic = first.geodesic(&B, &C);
+    let destination = first.destination(&A, 73.0, 500.0);
+    assert_eq!(first.save_snapshot(&path).unwrap(), 3);
+
+    let second = sync::DistanceCache::builder().build();
+    assert_eq!(second.load_snapshot(&path).unwrap(), 3);
+    assert_eq!(second.distance(&A, &B), distance);
+    assert_eq!(second.distance(&B, &A).distance, distance.distance);
+    assert_eq!(second.geodesic(&B, &C), geodesic);
+    assert_eq!(second.destination(&A, 73.0, 500.0), destination);
+    assert_eq!(second.solved(), 0);
+    assert_eq!(second.stats().insertions, 3);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn async_round_trip() {
+    let mut buf = Vec::new();
+    let first = DistanceCache::builder().build();
+    let distance = first.distance(&A, &C).await;
+    first.write_snapshot(&mut buf).unwrap();
+    // magic, format, crate version, ellipsoid, quantization, 3 sections
+    assert_eq!(buf.len(), 4 + 2 + 1 + env!("CARGO_PKG_VERSION").len() + 16 + 1 + 3 * 8 + 7 * 8);
+
+    let second = DistanceCache::builder().build();
+    assert_eq!(second.read_snapshot(&mut buf.as_slice()).await.unwrap(), 1);
+    assert_eq!(second.distance(&A, &C).await, distance);
+    assert_eq!(second.solved(), 0);
+}
+
+#[test]
+fn mismatched_caches_are_rejected() {
+    let mut buf = Vec::new();
+    let first = sync::DistanceCache::builder().build();
+    first.distance(&A, &B);
+    first.write_snapshot(&mut buf).unwrap();
+
+    let grs80 = sync::DistanceCache::builder().ellipsoid(Ellipsoid::GRS80).build();
+    match grs80.read_snapshot(&mut buf.as_slice()) {
+        Err(SnapshotError::EllipsoidMismatch(found)) => assert_eq!(found, Ellipsoid::WGS84),
+        other => panic!("unexpected {:?}", other),
+    }
+    let quantized = sync::DistanceCache::builder().quantization(Quantization::micro_degrees(10)).build();
+    match quantized.read_snapshot(&mut buf.as_slice()) {
+        Err(SnapshotError::QuantizationMismatch(Quantization::Exact)) => { },
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(grs80.entry_count() + quantized.entry_count(), 0);
+}
+
+#[test]
+fn garbage_is_rejected() {
+    let cache = sync::DistanceCache::builder().build();
+    match cache.read_snapshot(&mut &b"not a snapshot"[..]) {
+        Err(SnapshotError::NotASnapshot) => { },
+        other => panic!("unexpected {:?}", other),
+    }
+    match cache.read_snapshot(&mut &b"MKSN"[..]) {
+        Err(SnapshotError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof),
+        other => panic!("unexpected {:?}", other),
+    }
+    match cache.read_snapshot(&mut &b"MKSN\x02\x00"[..]) {
+        Err(SnapshotError::UnsupportedFormat(2)) => { },
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_entries_are_rejected() {
+    let cache = sync::DistanceCache::builder().build();
+    cache.distance(&A, &B);
+    let mut buf = Vec::new();
+    cache.write_snapshot(&mut buf).unwrap();
+    // magic, format, crate version, ellipsoid, quantization, entry count
+    let first_lat = 4 + 2 + 1 + env!("CARGO_PKG_VERSION").len() + 16 + 1 + 8;
+
+    // keys are stored southern-most first, so this mirrors `B`
+    for lat in [f64::NAN, 91.0, -B.get_lat()] {
+        let mut corrupt = buf.clone();
+        corrupt[first_lat..first_lat + 8].copy_from_slice(&lat.to_le_bytes());
+        let fresh = sync::DistanceCache::builder().build();
+        match fresh.read_snapshot(&mut corrupt.as_slice()) {
+            Err(SnapshotError::Corrupt) => { },
+            other => panic!("unexpected {:?} for latitude {}", other, lat),
+        }
+        assert_eq!(fresh.stats().insertions, 0);
+    }
+}
+
+#[test]
+fn failed_save_keeps_previous_snapshot() {
+    let path = temp_path("failed_save");
+    let first = sync::DistanceCache::builder().build();
+    first.distance(&A, &B);
+    assert_eq!(first.save_snapshot(&path).unwrap(), 1);
+
+    // nothing can be written where the temporary file should go
+    let mut temp = path.clone().into_os_string();
+    temp.push(".tmp");
+    std::fs::create_dir(&temp).unwrap();
+    let second = sync::DistanceCache::builder().build();
+    second.distance(&A, &C);
+    second.distance(&B, &C);
+    assert!(second.save_snapshot(&path).is_err());
+    std::fs::remove_dir(&temp).unwrap();
+
+    let third = sync::DistanceCache::builder().build();
+    assert_eq!(third.load_snapshot(&path).unwrap(), 1);
+    assert_eq!(second.save_snapshot(&path).unwrap(), 2);
+    assert!(!std::path::Path::new(&temp).exists());
+    std::fs::remove_file(&path).unwrap();
+}
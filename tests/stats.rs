@@ -0,0 +1,60 @@
+//! `stats()` must account for every lookup made through the cache.
+
+use memoized_kerney::{sync,DistanceCache,Position};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+const C: Position = Position::new(40.6413,-73.7781);
+
+#[test]
+fn sync_counts_hits_and_misses() {
+    let cache = sync::DistanceCache::builder().build();
+    assert_eq!(cache.stats().hit_rate(), 0.0);
+
+    cache.distance(&A, &B);
+    cache.distance(&B, &A);
+    cache.distance(&A, &B);
+    cache.destination(&A, 73.0, 500.0);
+
+    let stats = cache.stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.insertions, 2);
+    assert_eq!(stats.hit_rate(), 0.5);
+    assert!(stats.miss_compute_time > std::time::Duration::ZERO);
+    assert_eq!(stats.misses, cache.solved());
+}
+
+#[test]
+fn sync_batches_count_each_pair() {
+    let cache = sync::DistanceCache::builder().build();
+    cache.distance(&A, &B);
+    // the first A->B was a miss, in the matrix A->B is a hit while A->C,
+    // B->B and B->C are misses
+    cache.distance_matrix(&[A, B], &[B, C]);
+
+    let stats = cache.stats();
+    assert_eq!(stats.hits + stats.misses, 5);
+    assert_eq!(stats.misses, 4);
+    assert_eq!(stats.insertions, 4);
+}
+
+#[test]
+fn invalid_positions_are_not_counted() {
+    let cache = sync::DistanceCache::builder().build();
+    cache.distance(&Position::new(f64::NAN, 0.0), &A);
+    assert_eq!(cache.stats(), sync::DistanceCache::builder().build().stats());
+}
+
+#[tokio::test]
+async fn async_counts_hits_and_misses() {
+    let cache = DistanceCache::builder().build();
+    cache.distance(&A, &B).await;
+    cache.distance(&A, &B).await;
+    cache.geodesic(&A, &C).await;
+
+    let stats = cache.stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.insertions, 2);
+}
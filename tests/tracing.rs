@@ -0,0 +1,63 @@
+//! With the `tracing` feature lookups report hit/miss, flips and solver time.
+
+use std::{
+    io::{Write},
+    sync::{Arc,Mutex},
+};
+
+use tracing_subscriber::fmt::{format::FmtSpan};
+
+use memoized_kerney::{sync,uncached_distance,Position};
+
+const A: Position = Position::new(37.882704,-121.9807130);
+const B: Position = Position::new(37.883463,-121.980988);
+
+#[derive(Clone,Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn capture<F: FnOnce()>(func: F) -> Vec<String> {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, func);
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    output.lines().map(String::from).collect()
+}
+
+#[test]
+fn lookups_record_hit_miss_and_flip() {
+    let cache = sync::DistanceCache::builder().name("routing").build();
+    let lines = capture(|| {
+        cache.distance(&A, &B);
+        cache.distance(&B, &A);
+    });
+    let closed: Vec<&String> = lines.iter().filter(|line| line.contains("close")).collect();
+    assert_eq!(closed.len(), 2, "{:#?}", lines);
+    assert!(closed[0].contains("cache=\"routing\""), "{}", closed[0]);
+    assert!(closed[0].contains("hit=false"), "{}", closed[0]);
+    assert!(closed[0].contains("solve_time="), "{}", closed[0]);
+    assert!(closed[1].contains("hit=true"), "{}", closed[1]);
+    assert_ne!(closed[0].contains("flipped=true"), closed[1].contains("flipped=true"));
+    assert!(lines.iter().any(|line| line.contains("solved inverse problem")), "{:#?}", lines);
+}
+
+#[test]
+fn uncached_distance_has_a_span() {
+    let lines = capture(|| {
+        uncached_distance(&B, &A);
+    });
+    assert!(lines.iter().any(|line| line.contains("uncached_distance") && line.contains("solve_time=")), "{:#?}", lines);
+}
@@ -0,0 +1,159 @@
+//! Every fallible entry point must reject the same coordinates, and the
+//! infallible ones must never cache what they would reject.
+
+use memoized_kerney::{sync,DistanceCache,Ellipsoid,EllipsoidError,Position,PositionError,try_distance,try_distance_sync,try_uncached_distance,uncached_distance};
+
+const VALID: Position = Position::new(37.882704, -121.9807130);
+
+/// (latitude, longitude, expected error)
+fn invalid() -> Vec<(f64,f64,PositionError)> {
+    vec![
+        (f64::NAN, 0.0, PositionError::NaN),
+        (0.0, f64::NAN, PositionError::NaN),
+        (f64::NAN, f64::INFINITY, PositionError::NaN),
+        (91.0, 0.0, PositionError::LatitudeOutOfRange(91.0)),
+        (-91.0, 0.0, PositionError::LatitudeOutOfRange(-91.0)),
+        (f64::INFINITY, 0.0, PositionError::LatitudeOutOfRange(f64::INFINITY)),
+        (0.0, f64::INFINITY, PositionError::NonFiniteLongitude(f64::INFINITY)),
+        (0.0, f64::NEG_INFINITY, PositionError::NonFiniteLongitude(f64::NEG_INFINITY)),
+    ]
+}
+
+#[test]
+fn try_new_and_validate() {
+    for (lat, lon, error) in invalid() {
+        assert_eq!(Position::try_new(lat, lon), Result::Err(error), "({}, {})", lat, lon);
+        assert_eq!(Position::new(lat, lon).validate(), Result::Err(error), "({}, {})", lat, lon);
+    }
+    // the bounds themselves, and longitudes that only need wrapping, are fine
+    for (lat, lon) in [(90.0, 0.0), (-90.0, 0.0), (0.0, 540.0), (-0.0, -720.0), (37.882704, -121.9807130)] {
+        assert_eq!(Position::try_new(lat, lon), Result::Ok(Position::new(lat, lon)));
+        assert_eq!(Position::new(lat, lon).validate(), Result::Ok(Position::new(lat, lon)));
+    }
+}
+
+#[test]
+fn errors_display() {
+    assert_eq!(PositionError::NaN.to_string(), "coordinate is NaN");
+    assert_eq!(PositionError::LatitudeOutOfRange(91.0).to_string(), "latitude 91 is outside of [-90, 90]");
+    assert_eq!(PositionError::NonFiniteLongitude(f64::NEG_INFINITY).to_string(), "longitude -inf is not finite");
+    let boxed: Box<dyn std::error::Error> = Box::new(PositionError::NaN);
+    assert_eq!(boxed.to_string(), "coordinate is NaN");
+}
+
+#[test]
+fn uncached_rejects_invalid() {
+    assert_eq!(try_uncached_distance(&VALID, &VALID), Result::Ok(uncached_distance(&VALID, &VALID)));
+    for (lat, lon, error) in invalid() {
+        let pos = Position::new(lat, lon);
+        assert_eq!(try_uncached_distance(&pos, &VALID), Result::Err(error));
+        assert_eq!(try_uncached_distance(&VALID, &pos), Result::Err(error));
+    }
+}
+
+#[test]
+fn sync_rejects_invalid() {
+    let cache = sync::DistanceCache::builder().build();
+    for (lat, lon, error) in invalid() {
+        let pos = Position::new(lat, lon);
+        assert_eq!(cache.try_distance(&pos, &VALID), Result::Err(error));
+        assert_eq!(cache.try_distance(&VALID, &pos), Result::Err(error));
+        assert_eq!(try_distance_sync(&pos, &VALID), Result::Err(error));
+        assert_eq!(try_distance_sync(&VALID, &pos), Result::Err(error));
+    }
+    // rejected before the cache is consulted
+    assert_eq!(cache.stats().hits + cache.stats().misses, 0);
+    assert_eq!(try_distance_sync(&VALID, &VALID), Result::Ok(uncached_distance(&VALID, &VALID)));
+}
+
+#[tokio::test]
+async fn async_rejects_invalid() {
+    let cache = DistanceCache::builder().build();
+    for (lat, lon, error) in invalid() {
+        let pos = Position::new(lat, lon);
+        assert_eq!(cache.try_distance(&pos, &VALID).await, Result::Err(error));
+        assert_eq!(cache.try_distance(&VALID, &pos).await, Result::Err(error));
+        assert_eq!(try_distance(&pos, &VALID).await, Result::Err(error));
+        assert_eq!(try_distance(&VALID, &pos).await, Result::Err(error));
+    }
+    assert_eq!(cache.stats().hits + cache.stats().misses, 0);
+    assert_eq!(try_distance(&VALID, &VALID).await, Result::Ok(uncached_distance(&VALID, &VALID)));
+}
+
+/// Number of entries actually held, `entry_count` alone lags behind writes
+fn entries(cache: &sync::DistanceCache) -> u64 {
+    cache.write_snapshot(&mut Vec::new()).unwrap()
+}
+
+#[test]
+fn infallible_functions_never_cache_invalid() {
+    let cache = sync::DistanceCache::builder().build();
+    for (lat, lon, _) in invalid() {
+        let pos = Position::new(lat, lon);
+        cache.distance(&pos, &VALID);
+        cache.distance(&VALID, &pos);
+        cache.geodesic(&pos, &VALID);
+        cache.destination(&pos, 45.0, 1000.0);
+        cache.distances_from(&VALID, &[pos]);
+    }
+    assert_eq!(cache.entry_count(), 0);
+    assert_eq!(entries(&cache), 0);
+    assert_eq!(cache.stats().insertions, 0);
+
+    // while a valid pair is cached
+    cache.distance(&VALID, &Position::new(40.6413, -73.7781));
+    assert_eq!(entries(&cache), 1);
+    assert_eq!(cache.stats().insertions, 1);
+}
+
+#[tokio::test]
+async fn async_infallible_functions_never_cache_invalid() {
+    let cache = DistanceCache::builder().build();
+    for (lat, lon, _) in invalid() {
+        let pos = Position::new(lat, lon);
+        cache.distance(&pos, &VALID).await;
+        cache.geodesic(&VALID, &pos).await;
+        cache.destination(&pos, 45.0, 1000.0).await;
+    }
+    assert_eq!(cache.entry_count(), 0);
+    assert_eq!(cache.write_snapshot(&mut Vec::new()).unwrap(), 0);
+    assert_eq!(cache.stats().insertions, 0);
+}
+
+#[test]
+fn ellipsoid_try_new_and_validate() {
+    for (radius, flattening, error) in [
+        (f64::NAN, 0.0, EllipsoidError::EquatorialRadius(f64::NAN)),
+        (0.0, 0.0, EllipsoidError::EquatorialRadius(0.0)),
+        (-6378137.0, 0.0, EllipsoidError::EquatorialRadius(-6378137.0)),
+        (f64::INFINITY, 0.0, EllipsoidError::EquatorialRadius(f64::INFINITY)),
+        (6378137.0, 1.0, EllipsoidError::Flattening(1.0)),
+        (6378137.0, f64::NAN, EllipsoidError::Flattening(f64::NAN)),
+        (6378137.0, f64::NEG_INFINITY, EllipsoidError::Flattening(f64::NEG_INFINITY)),
+    ] {
+        // NaN never equals itself, compare the rendered errors
+        let found = Ellipsoid::try_new(radius, flattening).unwrap_err();
+        assert_eq!(found.to_string(), error.to_string(), "({}, {})", radius, flattening);
+        let found = Ellipsoid::new(radius, flattening).validate().unwrap_err();
+        assert_eq!(found.to_string(), error.to_string(), "({}, {})", radius, flattening);
+    }
+    // the bundled models, and prolate ones, are fine
+    for ellipsoid in [Ellipsoid::WGS84, Ellipsoid::SPHERE, Ellipsoid::MARS, Ellipsoid::new(6378137.0, -1.0 / 50.0)] {
+        assert_eq!(Ellipsoid::try_new(ellipsoid.equatorial_radius(), ellipsoid.flattening()), Result::Ok(ellipsoid));
+    }
+    assert_eq!(EllipsoidError::Flattening(1.0).to_string(), "flattening 1 is not finite and below 1");
+}
+
+#[test]
+fn invalid_ellipsoids_are_never_cached() {
+    let other = Position::new(40.6413, -73.7781);
+    for ellipsoid in [Ellipsoid::new(6378137.0, 1.0), Ellipsoid::new(f64::NAN, 0.0)] {
+        let cache = sync::DistanceCache::builder().ellipsoid(ellipsoid).build();
+        assert!(cache.distance(&VALID, &other).distance.is_nan());
+        cache.geodesic(&VALID, &other);
+        cache.destination(&VALID, 45.0, 1000.0);
+        cache.distances_from(&VALID, &[other]);
+        assert_eq!(entries(&cache), 0);
+        assert_eq!(cache.stats().insertions, 0);
+    }
+}